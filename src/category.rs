@@ -0,0 +1,182 @@
+//! Categories of scripts
+//!
+//! The scripts of the `default` category are in the `scripts` directory of each
+//! dotmgr directory, and those of a category like `work` are in `scripts-work`.
+//! The `categories` configuration key puts a category of the main repository in
+//! another directory. A `README.md` in the directory of a category describes it
+
+use std::{collections::BTreeSet, fs, path::PathBuf};
+
+use crate::config::{Config, DotfilesRepo, MAIN_REPO_NAME};
+
+/// The category of the `scripts` directory
+pub const DEFAULT_CATEGORY: &str = "default";
+
+/// The name that selects every category at once
+pub const ALL_CATEGORIES: &str = "all";
+
+/// The directory of a category in one repository
+#[derive(Clone)]
+pub struct CategoryDir {
+	/// The repository the directory is in
+	pub repo: DotfilesRepo,
+	/// The name of the category
+	pub category: String,
+	/// The directory, which may not exist
+	pub dir: PathBuf,
+}
+
+/// A category, with its directory in each repository
+#[derive(Clone)]
+pub struct Category {
+	pub name: String,
+	pub dirs: Vec<CategoryDir>,
+}
+
+impl Category {
+	/// Get the first paragraph of the `README.md` of the category, skipping
+	/// headings
+	pub fn description(&self) -> Option<String> {
+		let content = self
+			.dirs
+			.iter()
+			.find_map(|dir| fs::read_to_string(dir.dir.join("README.md")).ok())?;
+
+		let paragraph: Vec<&str> = content
+			.lines()
+			.map(str::trim)
+			.skip_while(|line| line.is_empty() || line.starts_with('#'))
+			.take_while(|line| !line.is_empty())
+			.collect();
+		match paragraph.is_empty() {
+			true => None,
+			false => Some(paragraph.join(" ")),
+		}
+	}
+}
+
+/// Get the directories of `category` in each repository. `None` is the
+/// default category, and `all` gets the directories of every category
+pub fn get_category_dirs(config: &Config, category: Option<&str>) -> Vec<CategoryDir> {
+	let category = category.unwrap_or(DEFAULT_CATEGORY);
+	if category == ALL_CATEGORIES {
+		return get_categories(config)
+			.into_iter()
+			.flat_map(|category| category.dirs)
+			.collect();
+	}
+
+	config
+		.repos
+		.iter()
+		.map(|repo| {
+			let dir = match config.categories.get(category) {
+				Some(dir) if repo.name == MAIN_REPO_NAME => dir.clone(),
+				_ if category == DEFAULT_CATEGORY => repo.dotmgr_dir.join("scripts"),
+				_ => repo.dotmgr_dir.join(format!("scripts-{category}")),
+			};
+			CategoryDir {
+				repo: repo.clone(),
+				category: String::from(category),
+				dir,
+			}
+		})
+		.collect()
+}
+
+/// Get every category: the default one, the ones in the configuration, and
+/// the ones with a directory in any repository
+pub fn get_categories(config: &Config) -> Vec<Category> {
+	let mut names: BTreeSet<String> = config.categories.keys().cloned().collect();
+	for repo in &config.repos {
+		let Ok(entries) = fs::read_dir(&repo.dotmgr_dir) else {
+			continue;
+		};
+		for entry in entries.flatten() {
+			let name = entry.file_name();
+			let Some(name) = name.to_str().and_then(|name| name.strip_prefix("scripts-")) else {
+				continue;
+			};
+			if entry.path().is_dir() && !name.is_empty() && name != ALL_CATEGORIES {
+				names.insert(String::from(name));
+			}
+		}
+	}
+	names.remove(DEFAULT_CATEGORY);
+
+	[String::from(DEFAULT_CATEGORY)]
+		.into_iter()
+		.chain(names)
+		.map(|name| Category {
+			dirs: get_category_dirs(config, Some(&name)),
+			name,
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		collections::{BTreeMap, HashMap},
+		fs,
+	};
+
+	use super::{get_categories, get_category_dirs, ALL_CATEGORIES};
+	use crate::{
+		config::{Config, ConflictPolicy},
+		test_util::{self, TempDir},
+		util,
+	};
+
+	#[test]
+	fn it_finds_categories() {
+		let dir = TempDir::new("category");
+		fs::create_dir_all(dir.join("scripts")).unwrap();
+		fs::create_dir_all(dir.join("scripts-work")).unwrap();
+		fs::create_dir_all(dir.join("games")).unwrap();
+		fs::write(dir.join("scripts/nvim.sh"), "").unwrap();
+		fs::write(dir.join("scripts-work/vpn.sh"), "").unwrap();
+		fs::write(
+			dir.join("scripts-work/README.md"),
+			"# Work\n\nScripts for the\nwork laptop\n\nMore",
+		)
+		.unwrap();
+		fs::write(dir.join("games/steam.sh"), "").unwrap();
+
+		let config = Config {
+			dotfiles_dir: dir.to_path_buf(),
+			os_dir: dir.to_path_buf(),
+			dotmgr_dir: dir.to_path_buf(),
+			conflict_policy: ConflictPolicy::Skip,
+			editor: String::from("vi"),
+			pager: Some(String::from("less")),
+			categories: BTreeMap::from([(String::from("games"), dir.join("games"))]),
+			repos: vec![test_util::repo(&dir)],
+			origins: HashMap::new(),
+		};
+
+		let categories = get_categories(&config);
+		let names: Vec<&str> = categories.iter().map(|val| val.name.as_str()).collect();
+		assert_eq!(names, vec!["default", "games", "work"]);
+		assert_eq!(categories[0].description(), None);
+		assert_eq!(
+			categories[2].description().as_deref(),
+			Some("Scripts for the work laptop")
+		);
+		assert_eq!(categories[1].dirs[0].dir, dir.join("games"));
+
+		let ids: Vec<String> = util::get_scripts(&get_category_dirs(&config, Some(ALL_CATEGORIES)))
+			.into_iter()
+			.map(|script| script.id)
+			.collect();
+		assert_eq!(
+			ids,
+			vec!["default/nvim.sh", "games/steam.sh", "work/vpn.sh"]
+		);
+		let ids: Vec<String> = util::get_scripts(&get_category_dirs(&config, None))
+			.into_iter()
+			.map(|script| script.id)
+			.collect();
+		assert_eq!(ids, vec!["nvim.sh"]);
+	}
+}
@@ -1,22 +1,61 @@
+use std::{path::PathBuf, time::Duration};
+
 use clap::{Parser, Subcommand};
 use clap_verbosity_flag::Verbosity;
 
+use fox_dotfile::{config::ConflictPolicy, metadata::parse_duration};
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
 	#[clap(flatten)]
 	pub verbose: Verbosity,
 
+	/// Use this dotfiles directory
+	#[arg(long, global = true)]
+	pub dotfiles_dir: Option<PathBuf>,
+
+	/// Use this OS-specific directory
+	#[arg(long, global = true)]
+	pub os_dir: Option<PathBuf>,
+
+	/// Use this dotmgr directory
+	#[arg(long, global = true)]
+	pub dotmgr_dir: Option<PathBuf>,
+
+	/// What to do when a target already exists: skip, backup, or overwrite
+	#[arg(long, global = true)]
+	pub conflict_policy: Option<ConflictPolicy>,
+
+	/// Use this editor
+	#[arg(long, global = true)]
+	pub editor: Option<String>,
+
+	/// Use this pager
+	#[arg(long, global = true)]
+	pub pager: Option<String>,
+
 	#[command(subcommand)]
 	pub command: CliCommands,
 }
 
 #[derive(Subcommand)]
 pub enum CliCommands {
+	/// Create a new dotmgr directory
+	Init {
+		/// Create it in this directory instead of the configured one
+		#[arg(long)]
+		dir: Option<PathBuf>,
+	},
+
+	/// Check the dotmgr directory and environment for problems
+	Doctor {},
+
 	/// Operate on a script
 	Script {
-		/// Choose where to select scripts from
-		#[arg(short)]
+		/// Choose the category to select scripts from, or `all` for every
+		/// category
+		#[arg(short, global = true)]
 		category: Option<String>,
 
 		#[command(subcommand)]
@@ -29,6 +68,24 @@ pub enum CliCommands {
 		command: ReconcileCommands,
 	},
 
+	/// Operate on the dotfiles repository
+	Repo {
+		#[command(subcommand)]
+		command: RepoCommands,
+	},
+
+	/// Operate on the configuration
+	Config {
+		#[command(subcommand)]
+		command: ConfigCommands,
+	},
+
+	/// Manage the background daemon
+	Daemon {
+		#[command(subcommand)]
+		command: DaemonCommands,
+	},
+
 	/// Run an internal command
 	Internal {
 		#[command(subcommand)]
@@ -41,19 +98,85 @@ pub enum ScriptCommands {
 	/// List scripts
 	List {},
 
+	/// List the categories of scripts
+	Categories {},
+
 	/// View a script
-	View { glob: Option<String> },
+	View { name: Option<String> },
 
 	/// Edit a script
-	Edit { glob: Option<String> },
+	Edit { name: Option<String> },
 
 	/// Run a script
 	Run {
-		/// run the script with elevated priviledges
+		/// Run the script as root, with sudo, doas, or pkexec
 		#[arg(long)]
 		sudo: bool,
 
-		glob: Option<String>,
+		/// Skip scripts that already ran successfully on this machine, unchanged
+		#[arg(long)]
+		once: bool,
+
+		/// Keep the output of the script in a log file
+		#[arg(long)]
+		log: bool,
+
+		/// Stop each script after this long, like 90s, 10m, or 2h, instead of
+		/// after its `timeout`
+		#[arg(long, value_parser = parse_duration)]
+		timeout: Option<Duration>,
+
+		name: Option<String>,
+
+		/// Arguments to pass to the script, after `--`
+		#[arg(last = true)]
+		args: Vec<String>,
+	},
+
+	/// Run every script, in order
+	RunAll {
+		/// Keep running scripts after one fails
+		#[arg(short, long)]
+		keep_going: bool,
+
+		/// Skip scripts that already ran successfully on this machine, unchanged
+		#[arg(long)]
+		once: bool,
+
+		/// Keep the output of each script in a log file
+		#[arg(long)]
+		log: bool,
+
+		/// Stop each script after this long, like 90s, 10m, or 2h, instead of
+		/// after its `timeout`
+		#[arg(long, value_parser = parse_duration)]
+		timeout: Option<Duration>,
+	},
+
+	/// Create a script from a template, and open it in the editor
+	New {
+		/// The name of the script. Without an extension, it is a shell script
+		name: String,
+
+		/// Create a directory with a `script` file inside it
+		#[arg(short, long)]
+		dir: bool,
+	},
+
+	/// Check scripts with shellcheck, and check their metadata
+	Lint { name: Option<String> },
+
+	/// Show when scripts ran, and whether they succeeded
+	History { name: Option<String> },
+
+	/// Show the log of the latest run of a script that was run with `--log`
+	Logs { name: Option<String> },
+
+	/// Print the scripts that each script requires
+	Graph {
+		/// Print the graph in the DOT language of Graphviz
+		#[arg(long)]
+		dot: bool,
 	},
 }
 
@@ -65,8 +188,64 @@ pub enum ReconcileCommands {
 	/// Deploy dotfiles
 	Deploy {},
 
-	// Undeploy dotfiles
+	/// Undeploy dotfiles
 	Undeploy {},
+
+	/// Watch the dotfiles directory and redeploy on changes
+	Watch {},
+}
+
+#[derive(Subcommand)]
+pub enum RepoCommands {
+	/// View status of the dotfiles repository
+	Status {},
+
+	/// Pull changes, then offer to deploy them
+	Pull {
+		/// Deploy without asking
+		#[arg(short, long)]
+		yes: bool,
+	},
+
+	/// Push changes
+	Push {},
+
+	/// Commit all changes
+	Commit {
+		/// Use the given commit message
+		#[arg(short, long)]
+		message: Option<String>,
+	},
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+	/// Print each configuration value and where it came from
+	Show {},
+}
+
+#[derive(Subcommand)]
+pub enum DaemonCommands {
+	/// Start the daemon in the foreground
+	Start {},
+
+	/// View status of dotfiles, as cached by the daemon
+	Status {},
+
+	/// Deploy dotfiles through the daemon
+	Deploy {},
+
+	/// Reload the dotfile list
+	Reload {},
+
+	/// Stop redeploying dotfiles on changes
+	Pause {},
+
+	/// Resume redeploying dotfiles on changes
+	Resume {},
+
+	/// Stop the daemon
+	Stop {},
 }
 
 #[derive(Subcommand)]
@@ -0,0 +1,24 @@
+use colored::Colorize;
+
+use fox_dotfile::config::Config;
+
+pub struct CommandConfig {
+	config: Config,
+}
+
+impl CommandConfig {
+	pub fn new(config: Config) -> Self {
+		Self { config }
+	}
+
+	pub fn show(&self) {
+		for (key, value, origin) in self.config.values() {
+			println!(
+				"{} {} {}",
+				format!("{}:", key).blue(),
+				value,
+				format!("({})", origin).dimmed()
+			);
+		}
+	}
+}
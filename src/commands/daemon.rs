@@ -0,0 +1,348 @@
+#[cfg(unix)]
+use std::{
+	env, fs,
+	io::{self, BufRead, BufReader, Read, Write},
+	os::unix::{
+		ffi::OsStrExt,
+		fs::{DirBuilderExt, MetadataExt, PermissionsExt},
+		net::{UnixListener, UnixStream},
+	},
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex},
+	thread,
+	time::Duration,
+};
+
+#[cfg(unix)]
+use colored::Colorize;
+
+use fox_dotfile::config::Config;
+use fox_dotfile::error::{Error, Result};
+use fox_dotfile::history;
+#[cfg(unix)]
+use fox_dotfile::reconcile::{
+	apply_changes, get_dotfile_list, reconcile_dotfiles, watch_dotfiles_dir, DotfileEntry,
+	ReconcileAction,
+};
+
+/// How long the daemon waits for a client to send a request or read the
+/// response
+#[cfg(unix)]
+const SERVER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a client waits for the daemon, which may be deploying
+#[cfg(unix)]
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The error of a request from a client with another configuration
+#[cfg(unix)]
+const CONFIG_MISMATCH: &str = "other configuration";
+
+pub struct CommandDaemon {
+	#[cfg_attr(not(unix), allow(dead_code))]
+	config: Config,
+}
+
+#[cfg(unix)]
+struct DaemonState {
+	dotfile_list: Vec<DotfileEntry>,
+	paused: bool,
+}
+
+impl CommandDaemon {
+	pub fn new(config: Config) -> Self {
+		Self { config }
+	}
+
+	#[cfg(unix)]
+	pub fn start(&self) -> Result<()> {
+		let socket_path = get_socket_path(&self.config.dotfiles_dir);
+
+		if daemon_request(&self.config, "ping").is_some() {
+			return Err(Error::Daemon {
+				message: format!(
+					"Daemon is already running for {}",
+					self.config.dotfiles_dir.display()
+				),
+			});
+		}
+		create_socket_dir(socket_path.parent().unwrap())?;
+		if socket_path.exists() {
+			fs::remove_file(&socket_path).map_err(|err| Error::io(&socket_path, err))?;
+		}
+		let listener =
+			UnixListener::bind(&socket_path).map_err(|err| Error::io(&socket_path, err))?;
+
+		let state = Arc::new(Mutex::new(DaemonState {
+			dotfile_list: get_dotfile_list(&self.config)?,
+			paused: false,
+		}));
+
+		{
+			let config = self.config.clone();
+			let state = Arc::clone(&state);
+			thread::spawn(move || {
+				let result = watch_dotfiles_dir(&config, |changed_paths| {
+					let mut state = state.lock().unwrap();
+					if state.paused {
+						return;
+					}
+
+					if let Err(err) = apply_changes(
+						&config,
+						&mut state.dotfile_list,
+						changed_paths,
+						&mut io::stdout(),
+					) {
+						eprintln!("{} {}", "error:".red(), err);
+					}
+				});
+				if let Err(err) = result {
+					eprintln!("{} {}", "error:".red(), err);
+				}
+			});
+		}
+
+		println!("Listening on {}", socket_path.display());
+
+		let key = get_config_key(&self.config);
+		for stream in listener.incoming() {
+			let mut stream = match stream {
+				Ok(val) => val,
+				Err(err) => {
+					log::error!("Failed to accept connection: {}", err);
+					continue;
+				}
+			};
+			// A client that never finishes its request must not block the
+			// daemon
+			if let Err(err) = stream
+				.set_read_timeout(Some(SERVER_TIMEOUT))
+				.and_then(|_| stream.set_write_timeout(Some(SERVER_TIMEOUT)))
+			{
+				log::error!("Failed to set timeouts: {}", err);
+				continue;
+			}
+
+			let mut line = String::new();
+			if let Err(err) = BufReader::new(&stream).read_line(&mut line) {
+				log::error!("Failed to read request: {}", err);
+				continue;
+			}
+			log::info!("request: {}", line.trim());
+			let (request, request_key) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+
+			// The first line of the response is its status, then the output
+			let mut output: Vec<u8> = vec![];
+			let result: std::result::Result<(), String> = if request_key != key {
+				Err(String::from(CONFIG_MISMATCH))
+			} else {
+				let mut state = state.lock().unwrap();
+				match request {
+					"ping" => {
+						writeln!(output, "pong").unwrap();
+						Ok(())
+					}
+					"status" => reconcile_dotfiles(
+						&state.dotfile_list,
+						ReconcileAction::Status,
+						self.config.conflict_policy,
+						&mut output,
+					)
+					.map_err(|err| err.to_string()),
+					"deploy" => reconcile_dotfiles(
+						&state.dotfile_list,
+						ReconcileAction::Deploy,
+						self.config.conflict_policy,
+						&mut output,
+					)
+					.map_err(|err| err.to_string()),
+					"reload" => match get_dotfile_list(&self.config) {
+						Ok(val) => {
+							state.dotfile_list = val;
+							writeln!(output, "Reloaded {} dotfiles", state.dotfile_list.len()).unwrap();
+							Ok(())
+						}
+						Err(err) => Err(format!("Failed to reload dotfile list: {}", err)),
+					},
+					"pause" => {
+						state.paused = true;
+						writeln!(output, "Paused").unwrap();
+						Ok(())
+					}
+					"resume" => {
+						state.paused = false;
+						writeln!(output, "Resumed").unwrap();
+						Ok(())
+					}
+					"stop" => {
+						stream.write_all(b"ok\nStopping\n").ok();
+						return fs::remove_file(&socket_path).map_err(|err| Error::io(&socket_path, err));
+					}
+					_ => Err(format!("Unknown request: {}", request)),
+				}
+			};
+
+			let status = match result {
+				Ok(()) => String::from("ok"),
+				// Messages are kept on the status line
+				Err(message) => format!("error {}", message.replace('\n', " ")),
+			};
+			if let Err(err) = writeln!(stream, "{}", status).and_then(|_| stream.write_all(&output)) {
+				log::error!("Failed to write response: {}", err);
+			}
+		}
+
+		Ok(())
+	}
+
+	#[cfg(not(unix))]
+	pub fn start(&self) -> Result<()> {
+		Err(Error::Unsupported {
+			message: String::from("The daemon is only supported on Unix"),
+		})
+	}
+
+	pub fn request(&self, request: &str) -> Result<()> {
+		match daemon_request(&self.config, request) {
+			None => Err(Error::Daemon {
+				message: format!(
+					"Daemon is not running for {}",
+					self.config.dotfiles_dir.display()
+				),
+			}),
+			Some(DaemonResponse::Ok(output)) => {
+				print!("{}", output);
+				Ok(())
+			}
+			Some(DaemonResponse::Failed { output, message }) => {
+				print!("{}", output);
+				Err(Error::Daemon { message })
+			}
+			Some(DaemonResponse::OtherConfig) => Err(Error::Daemon {
+				message: format!(
+					"Daemon for {} runs with another configuration. Restart it with this one",
+					self.config.dotfiles_dir.display()
+				),
+			}),
+		}
+	}
+}
+
+/// The response of the daemon to a request
+pub enum DaemonResponse {
+	/// The request succeeded, with its output
+	Ok(String),
+	/// The request failed with `message`, after printing `output`
+	Failed { output: String, message: String },
+	/// The daemon resolved another configuration than the client, so its
+	/// answers would not apply
+	OtherConfig,
+}
+
+/// Get a hash of the values of `config` that the daemon answers with. A
+/// request is only answered if the client has the same one
+pub fn get_config_key(config: &Config) -> String {
+	let mut values = vec![
+		config.dotfiles_dir.display().to_string(),
+		config.os_dir.display().to_string(),
+		config.dotmgr_dir.display().to_string(),
+		config.conflict_policy.to_string(),
+	];
+	for repo in &config.repos {
+		values.push(format!(
+			"{}:{}:{}:{}",
+			repo.name,
+			repo.dotfiles_dir.display(),
+			repo.os_dir.display(),
+			repo.dotmgr_dir.display()
+		));
+	}
+
+	history::content_hash(values.join("\n").as_bytes())
+}
+
+pub fn get_socket_path(dotfiles_dir: &Path) -> PathBuf {
+	let dotfiles_dir = dotfiles_dir
+		.canonicalize()
+		.unwrap_or_else(|_| dotfiles_dir.to_path_buf());
+	let hash = history::content_hash(dotfiles_dir.as_os_str().as_bytes());
+	get_socket_dir().join(format!("daemon-{}.sock", hash))
+}
+
+/// Get the directory of the sockets: `dotfile` in `$XDG_RUNTIME_DIR`, or else
+/// one for the current user in the temporary directory
+#[cfg(unix)]
+fn get_socket_dir() -> PathBuf {
+	match dirs::runtime_dir() {
+		Some(dir) => dir.join("dotfile"),
+		None => env::temp_dir().join(format!("dotfile-{}", unsafe { libc::getuid() })),
+	}
+}
+
+/// Create the directory of the sockets, so that only the current user can
+/// access it
+#[cfg(unix)]
+fn create_socket_dir(dir: &Path) -> Result<()> {
+	fs::DirBuilder::new()
+		.recursive(true)
+		.mode(0o700)
+		.create(dir)
+		.map_err(|err| Error::io(dir, err))?;
+	// The directory may be from before it was private
+	let metadata = fs::symlink_metadata(dir).map_err(|err| Error::io(dir, err))?;
+	if metadata.uid() == unsafe { libc::getuid() } && metadata.mode() & 0o077 != 0 {
+		fs::set_permissions(dir, fs::Permissions::from_mode(0o700))
+			.map_err(|err| Error::io(dir, err))?;
+	}
+
+	check_socket_dir(dir).map_err(|err| Error::io(dir, err))
+}
+
+/// Check that the directory of the sockets is only accessible by the current
+/// user, so that no one else can pose as the daemon
+#[cfg(unix)]
+fn check_socket_dir(dir: &Path) -> io::Result<()> {
+	let metadata = fs::symlink_metadata(dir)?;
+	match metadata.is_dir()
+		&& metadata.uid() == unsafe { libc::getuid() }
+		&& metadata.mode() & 0o077 == 0
+	{
+		true => Ok(()),
+		false => Err(io::Error::new(
+			io::ErrorKind::PermissionDenied,
+			"the directory of the daemon socket must be a private directory of the current user",
+		)),
+	}
+}
+
+/// Send a request to the daemon for the dotfiles of `config`, returning `None`
+/// if one cannot be reached
+#[cfg(unix)]
+pub fn daemon_request(config: &Config, request: &str) -> Option<DaemonResponse> {
+	let socket_path = get_socket_path(&config.dotfiles_dir);
+	check_socket_dir(socket_path.parent().unwrap()).ok()?;
+	let mut stream = UnixStream::connect(socket_path).ok()?;
+	stream.set_read_timeout(Some(CLIENT_TIMEOUT)).ok()?;
+	stream.set_write_timeout(Some(CLIENT_TIMEOUT)).ok()?;
+	writeln!(stream, "{} {}", request, get_config_key(config)).ok()?;
+
+	let mut response = String::new();
+	stream.read_to_string(&mut response).ok()?;
+	let (status, output) = response.split_once('\n')?;
+	match status {
+		"ok" => Some(DaemonResponse::Ok(String::from(output))),
+		_ => match status.strip_prefix("error ")? {
+			CONFIG_MISMATCH => Some(DaemonResponse::OtherConfig),
+			message => Some(DaemonResponse::Failed {
+				output: String::from(output),
+				message: String::from(message),
+			}),
+		},
+	}
+}
+
+#[cfg(not(unix))]
+pub fn daemon_request(_config: &Config, _request: &str) -> Option<DaemonResponse> {
+	None
+}
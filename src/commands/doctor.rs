@@ -0,0 +1,252 @@
+use std::{fs, path::Path, process::Command};
+
+use colored::Colorize;
+
+use fox_dotfile::config::{Config, DotfilesRepo};
+use fox_dotfile::environment;
+use fox_dotfile::error::{Error, Result};
+use fox_dotfile::reconcile::{get_deploy_sh, parse_dotfile_line, run_deploy_sh};
+use fox_dotfile::util;
+
+pub struct CommandDoctor {
+	config: Config,
+}
+
+#[derive(Default)]
+struct Diagnostics {
+	problems: usize,
+}
+
+impl Diagnostics {
+	fn section(&self, title: &str) {
+		println!("{}", title.bold());
+	}
+
+	fn ok(&self, message: &str) {
+		println!("  {} {}", "✔".green(), message);
+	}
+
+	fn skipped(&self, message: &str) {
+		println!("  {} {}", "-".dimmed(), message.dimmed());
+	}
+
+	fn problem(&mut self, message: &str, hint: &str) {
+		println!("  {} {}", "✘".red(), message);
+		println!("    => {} {}", "hint:".dimmed(), hint);
+		self.problems += 1;
+	}
+}
+
+impl CommandDoctor {
+	pub fn new(config: Config) -> Self {
+		Self { config }
+	}
+
+	pub fn doctor(&self) -> Result<()> {
+		let mut diagnostics = Diagnostics::default();
+
+		for repo in &self.config.repos {
+			// Only label sections when there is more than one repository
+			let section = |title: &str| match self.config.repos.len() {
+				1 => String::from(title),
+				_ => format!("{} ({})", title, repo.name),
+			};
+			let entrypoint_sh = util::get_entrypoint_sh(&repo.dotmgr_dir);
+			let environment_sh = util::get_environment_sh(&repo.dotmgr_dir);
+			let deploy_sh = get_deploy_sh(&repo.dotmgr_dir);
+
+			diagnostics.section(&section("Directories"));
+			for (name, dir) in [
+				("dotfiles_dir", &repo.dotfiles_dir),
+				("os_dir", &repo.os_dir),
+				("dotmgr_dir", &repo.dotmgr_dir),
+			] {
+				if dir.is_dir() {
+					diagnostics.ok(&format!("{} exists: {}", name, dir.display()));
+				} else {
+					diagnostics.problem(
+						&format!("{} does not exist: {}", name, dir.display()),
+						"Set DOTMGR_DOTFILES_DIR to your dotfiles, or create the directory with `dotfile init`",
+					);
+				}
+			}
+
+			diagnostics.section(&section("Scripts"));
+			for script in [&entrypoint_sh, &environment_sh, &deploy_sh] {
+				check_script(&mut diagnostics, script);
+			}
+
+			diagnostics.section(&section("Environment"));
+			if util::is_executable(&environment_sh) {
+				check_environment_sh(&mut diagnostics, &environment_sh);
+			} else {
+				diagnostics.skipped("Skipped running environment.sh, as it is not executable");
+			}
+
+			diagnostics.section(&section("Deploy"));
+			if util::is_executable(&deploy_sh) {
+				check_deploy_sh(&mut diagnostics, repo);
+			} else {
+				diagnostics.skipped("Skipped running deploy.sh, as it is not executable");
+			}
+		}
+
+		diagnostics.section("Programs");
+		check_program(
+			&mut diagnostics,
+			"editor",
+			&self.config.editor,
+			"Set VISUAL or EDITOR, or `editor` in the config file, to an installed editor",
+		);
+		check_program(
+			&mut diagnostics,
+			"pager",
+			&self.config.pager(),
+			"Set PAGER, or `pager` in the config file, to an installed pager",
+		);
+
+		println!();
+		if diagnostics.problems > 0 {
+			return Err(Error::Doctor {
+				problems: diagnostics.problems,
+			});
+		}
+
+		println!("No problems found");
+		Ok(())
+	}
+}
+
+fn check_deploy_sh(diagnostics: &mut Diagnostics, repo: &DotfilesRepo) {
+	let output = match run_deploy_sh(&repo.dotmgr_dir) {
+		Ok(val) => val,
+		Err(err) => {
+			diagnostics.problem(
+				&format!("deploy.sh failed to start: {}", err),
+				"Check the shebang of deploy.sh",
+			);
+			return;
+		}
+	};
+
+	if !output.status.success() {
+		diagnostics.problem(
+			&format!("deploy.sh exited with {}", output.status),
+			&format!(
+				"Fix the errors printed by deploy.sh: {}",
+				String::from_utf8_lossy(&output.stderr).trim()
+			),
+		);
+		return;
+	}
+	diagnostics.ok("deploy.sh runs successfully");
+
+	let mut count = 0;
+	for (i, line) in String::from_utf8_lossy(&output.stdout).lines().enumerate() {
+		match parse_dotfile_line(line, &repo.name) {
+			Ok(Some(dotfile)) => {
+				count += 1;
+				if !dotfile.source.exists() {
+					diagnostics.problem(
+						&format!(
+							"deploy.sh line {}: source does not exist: {}",
+							i + 1,
+							dotfile.source.display()
+						),
+						"Create the source file, or remove the line from deploy.sh",
+					);
+				}
+			}
+			Ok(None) => {}
+			Err(err) => {
+				diagnostics.problem(
+					&format!("deploy.sh line {}: {}: {}", i + 1, err, line),
+					"Print lines in the form 'op|source|target', where 'op' is 'symlink' or 'copy'",
+				);
+			}
+		}
+	}
+	diagnostics.ok(&format!("deploy.sh lists {} dotfile(s)", count));
+}
+
+fn check_script(diagnostics: &mut Diagnostics, script: &Path) {
+	let name = script.display();
+
+	if !script.is_file() {
+		diagnostics.problem(
+			&format!("Script does not exist: {}", name),
+			"Create it with `dotfile init`",
+		);
+		return;
+	}
+
+	if util::is_executable(script) {
+		diagnostics.ok(&format!("Script is executable: {}", name));
+	} else {
+		diagnostics.problem(
+			&format!("Script is not executable: {}", name),
+			&format!("Run `chmod +x {}`", name),
+		);
+	}
+
+	match fs::read(script) {
+		Ok(content) if content.starts_with(b"#!") => {
+			diagnostics.ok(&format!("Script has a shebang: {}", name));
+		}
+		_ => {
+			diagnostics.problem(
+				&format!("Script does not have a shebang: {}", name),
+				"Add a shebang like `#!/usr/bin/env bash` as the first line",
+			);
+		}
+	}
+}
+
+fn check_environment_sh(diagnostics: &mut Diagnostics, environment_sh: &Path) {
+	let output = match Command::new(environment_sh).output() {
+		Ok(val) => val,
+		Err(err) => {
+			diagnostics.problem(
+				&format!("environment.sh failed to start: {}", err),
+				"Check the shebang of environment.sh",
+			);
+			return;
+		}
+	};
+
+	if !output.status.success() {
+		diagnostics.problem(
+			&format!("environment.sh exited with {}", output.status),
+			&format!(
+				"Fix the errors printed by environment.sh: {}",
+				String::from_utf8_lossy(&output.stderr).trim()
+			),
+		);
+		return;
+	}
+	diagnostics.ok("environment.sh runs successfully");
+
+	match environment::parse(&String::from_utf8_lossy(&output.stdout)) {
+		Ok(vars) => diagnostics.ok(&format!("environment.sh sets {} variable(s)", vars.len())),
+		Err(err) => diagnostics.problem(
+			&format!("environment.sh output line {}: {}", err.line, err.message),
+			"Print lines in the form 'KEY=VALUE', quoted like in a shell",
+		),
+	}
+}
+
+fn check_program(diagnostics: &mut Diagnostics, name: &str, command_line: &str, hint: &str) {
+	let program = command_line.split_whitespace().next().unwrap_or_default();
+
+	match util::find_command(program) {
+		Some(path) => {
+			diagnostics.ok(&format!("{} is available: {}", name, path.display()));
+		}
+		None => {
+			diagnostics.problem(
+				&format!("{} is not available: {}", name, command_line),
+				hint,
+			);
+		}
+	}
+}
@@ -0,0 +1,94 @@
+use std::{
+	fs,
+	path::{Path, PathBuf},
+};
+
+use colored::Colorize;
+
+use fox_dotfile::config::Config;
+use fox_dotfile::error::{Error, Result};
+
+/// Files of the skeleton, relative to the dotmgr directory, and whether they
+/// should be executable
+const SKELETON: [(&str, &str, bool); 5] = [
+	(
+		"impl/entrypoint.sh",
+		include_str!("../templates/init/impl/entrypoint.sh"),
+		true,
+	),
+	(
+		"impl/environment.sh",
+		include_str!("../templates/init/impl/environment.sh"),
+		true,
+	),
+	(
+		"impl/deploy.sh",
+		include_str!("../templates/init/impl/deploy.sh"),
+		true,
+	),
+	(
+		"util/log.sh",
+		include_str!("../templates/init/util/log.sh"),
+		false,
+	),
+	(
+		"scripts/example.sh",
+		include_str!("../templates/init/scripts/example.sh"),
+		true,
+	),
+];
+
+pub struct CommandInit {
+	config: Config,
+}
+
+impl CommandInit {
+	pub fn new(config: Config) -> Self {
+		Self { config }
+	}
+
+	pub fn init(&self, dir: Option<PathBuf>) -> Result<()> {
+		let dir = match dir {
+			Some(val) => val,
+			None => self.config.dotmgr_dir.clone(),
+		};
+
+		for subdir in ["impl", "util", "scripts", "extras"] {
+			let path = dir.join(subdir);
+			fs::create_dir_all(&path).map_err(|err| Error::io(&path, err))?;
+		}
+
+		for (file, content, executable) in SKELETON {
+			let path = dir.join(file);
+
+			if path.exists() {
+				println!("{} {}", "skipped:".dimmed(), path.display());
+				continue;
+			}
+
+			fs::write(&path, content).map_err(|err| Error::io(&path, err))?;
+			if executable {
+				set_executable(&path)?;
+			}
+			println!("{} {}", "created:".dimmed(), path.display());
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(unix)]
+pub fn set_executable(path: &Path) -> Result<()> {
+	use std::os::unix::fs::PermissionsExt;
+
+	let mut permissions = fs::metadata(path)
+		.map_err(|err| Error::io(path, err))?
+		.permissions();
+	permissions.set_mode(permissions.mode() | 0o111);
+	fs::set_permissions(path, permissions).map_err(|err| Error::io(path, err))
+}
+
+#[cfg(not(unix))]
+pub fn set_executable(_path: &Path) -> Result<()> {
+	Ok(())
+}
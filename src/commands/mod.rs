@@ -6,3 +6,18 @@ pub use reconcile::*;
 
 mod script;
 pub use script::*;
+
+mod daemon;
+pub use daemon::*;
+
+mod repo;
+pub use repo::*;
+
+mod init;
+pub use init::*;
+
+mod doctor;
+pub use doctor::*;
+
+mod config;
+pub use config::*;
@@ -0,0 +1,213 @@
+use std::{
+	io::{self, Write},
+	path::{Path, PathBuf},
+	process::Command,
+};
+
+use colored::Colorize;
+
+use crate::commands::CommandReconcile;
+use fox_dotfile::config::Config;
+use fox_dotfile::error::{Error, Result};
+
+pub struct CommandRepo {
+	config: Config,
+}
+
+impl CommandRepo {
+	pub fn new(config: Config) -> Self {
+		Self { config }
+	}
+
+	pub fn status(&self) -> Result<()> {
+		run_git(&self.config.dotfiles_dir, &["status"])
+	}
+
+	pub fn pull(&self, yes: bool) -> Result<()> {
+		run_git(&self.config.dotfiles_dir, &["pull"])?;
+
+		if yes || prompt("Deploy dotfiles now?")? {
+			CommandReconcile::new(self.config.clone()).deploy()?;
+		}
+
+		Ok(())
+	}
+
+	pub fn push(&self) -> Result<()> {
+		run_git(&self.config.dotfiles_dir, &["push"])
+	}
+
+	pub fn commit(&self, message: Option<String>) -> Result<()> {
+		match message {
+			Some(val) => run_git(&self.config.dotfiles_dir, &["commit", "--all", "-m", &val]),
+			None => run_git(&self.config.dotfiles_dir, &["commit", "--all"]),
+		}
+	}
+}
+
+fn git(dir: &Path) -> Command {
+	let mut command = Command::new("git");
+	command.arg("-C").arg(dir);
+	command
+}
+
+fn run_git(dir: &Path, args: &[&str]) -> Result<()> {
+	log::info!("git: {}", args.join(" "));
+
+	// git prints its own errors, so stderr is not captured
+	let status = git(dir)
+		.args(args)
+		.status()
+		.map_err(|err| Error::spawn(Path::new("git"), err))?;
+	if !status.success() {
+		return Err(Error::CommandFailed {
+			command: PathBuf::from(format!("git {}", args[0])),
+			status,
+			stderr: String::new(),
+		});
+	}
+
+	Ok(())
+}
+
+/// Ask a yes or no question on the terminal. Anything but yes is no
+pub(super) fn prompt(question: &str) -> Result<bool> {
+	print!("{} [y/N] ", question);
+	io::stdout()
+		.flush()
+		.map_err(|err| Error::io(Path::new("<stdout>"), err))?;
+
+	let mut answer = String::new();
+	io::stdin()
+		.read_line(&mut answer)
+		.map_err(|err| Error::io(Path::new("<stdin>"), err))?;
+
+	Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Get warnings about the state of the dotfiles repository. Nothing is
+/// returned if the directory is not a Git repository
+pub fn get_repo_warnings(dir: &Path) -> Vec<String> {
+	let mut warnings = vec![];
+
+	let output = match git(dir).args(["status", "--porcelain"]).output() {
+		Ok(val) if val.status.success() => val,
+		_ => return warnings,
+	};
+	if !output.stdout.is_empty() {
+		warnings.push(String::from("Dotfiles repository has uncommitted changes"));
+	}
+
+	// Only compares against the last fetched upstream ref
+	if let Ok(output) = git(dir)
+		.args(["rev-list", "--count", "HEAD..@{upstream}"])
+		.output()
+	{
+		if output.status.success() {
+			let behind = String::from_utf8_lossy(&output.stdout)
+				.trim()
+				.parse::<u32>()
+				.unwrap_or(0);
+			if behind > 0 {
+				warnings.push(format!(
+					"Dotfiles repository is behind its upstream by {} commit(s)",
+					behind
+				));
+			}
+		}
+	}
+
+	warnings
+}
+
+pub fn print_repo_warnings(dir: &Path) {
+	for warning in get_repo_warnings(dir) {
+		eprintln!("{} {}", "WARNING:".yellow(), warning);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{get_repo_warnings, git};
+	use std::{
+		env, fs,
+		ops::Deref,
+		path::{Path, PathBuf},
+		process,
+	};
+
+	/// An empty temporary directory, removed when dropped, so that failing
+	/// tests do not leave it behind
+	struct TempDir {
+		path: PathBuf,
+	}
+
+	impl TempDir {
+		fn new(name: &str) -> TempDir {
+			let path = env::temp_dir().join(format!("dotfile-{}-test-{}", name, process::id()));
+			let _ = fs::remove_dir_all(&path);
+			fs::create_dir_all(&path).unwrap();
+
+			TempDir { path }
+		}
+	}
+
+	impl Deref for TempDir {
+		type Target = Path;
+
+		fn deref(&self) -> &Path {
+			&self.path
+		}
+	}
+
+	impl Drop for TempDir {
+		fn drop(&mut self) {
+			let _ = fs::remove_dir_all(&self.path);
+		}
+	}
+
+	fn run(dir: &Path, args: &[&str]) {
+		let status = git(dir)
+			.args(["-c", "user.name=test", "-c", "user.email=test@test"])
+			.args(args)
+			.output()
+			.unwrap()
+			.status;
+		assert!(status.success(), "git {:?} failed", args);
+	}
+
+	#[test]
+	fn it_warns_about_repository_state() {
+		let root = TempDir::new("repo");
+
+		assert!(get_repo_warnings(&root).is_empty());
+
+		run(&root, &["init", "--bare", "upstream.git"]);
+		run(&root, &["clone", "upstream.git", "a"]);
+		let a = root.join("a");
+
+		fs::write(a.join("file"), "woof").unwrap();
+		assert_eq!(
+			get_repo_warnings(&a),
+			vec!["Dotfiles repository has uncommitted changes"]
+		);
+
+		run(&a, &["add", "file"]);
+		run(&a, &["commit", "-m", "Add file"]);
+		run(&a, &["push", "origin", "HEAD"]);
+		assert!(get_repo_warnings(&a).is_empty());
+
+		run(&root, &["clone", "upstream.git", "b"]);
+		let b = root.join("b");
+		assert!(get_repo_warnings(&b).is_empty());
+
+		fs::write(a.join("file"), "bark").unwrap();
+		run(&a, &["commit", "--all", "-m", "Change file"]);
+		run(&a, &["push", "origin", "HEAD"]);
+		run(&b, &["fetch"]);
+		assert_eq!(
+			get_repo_warnings(&b),
+			vec!["Dotfiles repository is behind its upstream by 1 commit(s)"]
+		);
+	}
+}
@@ -1,90 +1,1008 @@
-use crate::config::Config;
 use colored::Colorize;
-use std::path::PathBuf;
-use std::process::{exit, Command};
+use fox_dotfile::category::{self, CategoryDir, ALL_CATEGORIES, DEFAULT_CATEGORY};
+use fox_dotfile::config::{Config, MAIN_REPO_NAME};
+use std::env;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use crate::util;
+use fox_dotfile::error::{Error, Result};
+use fox_dotfile::graph::ScriptGraph;
+use fox_dotfile::history::{self, ScriptRun};
+use fox_dotfile::lint::{self, Finding, Severity};
+use fox_dotfile::metadata::{format_duration, ScriptInfo};
+use fox_dotfile::process::{self, Outcome};
+use fox_dotfile::util::{self, Runner, Script, ScriptMatch};
+
+use super::init::set_executable;
+use super::repo::prompt;
+use crate::tui;
+
+/// The template for new shell scripts, where `{{name}}` is the script name
+const SCRIPT_TEMPLATE: &str = include_str!("../templates/new/script.sh");
 
 pub struct CommandScript {
 	config: Config,
-	category_path: PathBuf,
+	// The category to use, or `all`
+	category: String,
+	// The directory of the category in each repository
+	category_dirs: Vec<CategoryDir>,
 }
 
 impl CommandScript {
 	pub fn new(config: Config, category: Option<String>) -> Self {
-		let category_path = PathBuf::from(&config.dotmgr_dir).join(match category {
-			Some(val) => format!("scripts-{val}"),
-			None => String::from("scripts"),
-		});
+		let category = category.unwrap_or_else(|| String::from(DEFAULT_CATEGORY));
+		let category_dirs = category::get_category_dirs(&config, Some(&category));
 
 		Self {
 			config,
-			category_path,
+			category,
+			category_dirs,
+		}
+	}
+
+	/// Build the graph of the scripts of the category, and of every other
+	/// category, so that scripts can require scripts of other categories
+	fn get_graph(&self) -> ScriptGraph {
+		let mut scripts = util::get_scripts(&self.category_dirs);
+		if self.category != ALL_CATEGORIES {
+			let all_dirs = category::get_category_dirs(&self.config, Some(ALL_CATEGORIES));
+			let others = util::get_scripts(&all_dirs)
+				.into_iter()
+				.filter(|script| script.category != self.category);
+			scripts.extend(others);
 		}
+
+		ScriptGraph::new(scripts)
+	}
+
+	/// Whether `script` is in the category of the command
+	fn is_in_category(&self, script: &Script) -> bool {
+		self.category == ALL_CATEGORIES || script.category == self.category
+	}
+
+	pub fn categories(&self) {
+		let rows: Vec<Vec<String>> = category::get_categories(&self.config)
+			.into_iter()
+			.map(|category| {
+				vec![
+					category.name.clone(),
+					util::get_scripts(&category.dirs).len().to_string(),
+					category.description().unwrap_or_default(),
+				]
+			})
+			.collect();
+
+		print_table(&["NAME", "SCRIPTS", "DESCRIPTION"], &rows);
 	}
 
 	pub fn list(&self) {
-		std::process::Command::new("ls")
-			.args(["-x", self.category_path.to_str().unwrap()])
-			.spawn()
-			.unwrap();
+		let mut rows = vec![];
+		for script in util::get_scripts(&self.category_dirs) {
+			let info = script.info();
+			let supported = info
+				.as_ref()
+				.map_or(true, |info| info.supports_current_os());
+			let row = match info {
+				Ok(info) => vec![
+					script.id,
+					info
+						.order
+						.map(|order| order.to_string())
+						.unwrap_or_default(),
+					match info.os.is_empty() {
+						true => String::from("any"),
+						false => info.os.join(","),
+					},
+					String::from(if info.requires_sudo { "yes" } else { "" }),
+					info.tags.join(","),
+					info.description.unwrap_or_default(),
+				],
+				Err(err) => vec![
+					script.id,
+					String::new(),
+					String::new(),
+					String::new(),
+					String::new(),
+					match err {
+						Error::Metadata { line, message, .. } => {
+							format!("invalid metadata: line {}: {}", line, message)
+						}
+						err => err.to_string(),
+					}
+					.red()
+					.to_string(),
+				],
+			};
+			// Scripts for other operating systems are dimmed
+			rows.push(match supported {
+				true => row,
+				false => row.iter().map(|cell| cell.dimmed().to_string()).collect(),
+			});
+		}
+
+		print_table(
+			&["NAME", "ORDER", "OS", "SUDO", "TAGS", "DESCRIPTION"],
+			&rows,
+		);
+	}
+
+	pub fn view(&self, query: Option<String>) -> Result<()> {
+		let Some(script) = self.get_script_exec(query)? else {
+			return Ok(());
+		};
+		let pager = self.config.pager();
+		log::info!("pager: {}", pager);
+
+		open_with(&pager, &script.path)
+	}
+
+	pub fn edit(&self, query: Option<String>) -> Result<()> {
+		let Some(script) = self.get_script_exec(query)? else {
+			return Ok(());
+		};
+		log::info!("editor: {}", self.config.editor);
+
+		open_with(&self.config.editor, &script.path)
+	}
+
+	pub fn run(
+		&self,
+		query: Option<String>,
+		args: &[String],
+		sudo: bool,
+		once: bool,
+		log: bool,
+		timeout: Option<Duration>,
+	) -> Result<()> {
+		let Some(script) = self.get_script_exec(query)? else {
+			return Ok(());
+		};
+
+		let history = match once {
+			true => history::read_history(&history::get_history_path())?,
+			false => vec![],
+		};
+		let host = util::get_hostname();
+		if once && already_ran(&history, &host, &script)? {
+			println!("{} already ran successfully on {}", script.id, host);
+			return Ok(());
+		}
+
+		let graph = self.get_graph();
+		let (info, order) = match script.is_runnable() {
+			true => (graph.info(&script)?.clone(), graph.run_order(&[&script])?),
+			false => (ScriptInfo::default(), vec![]),
+		};
+		info
+			.check_arguments(args)
+			.map_err(|message| Error::script(format!("{}: {}", script.id, message)))?;
+
+		// Ask whether to run as root before running anything
+		let mut required = vec![];
+		for val in order.iter().filter(|val| val.file() != script.file()) {
+			if !(once && already_ran(&history, &host, val)?) {
+				// Required scripts run without arguments
+				let info = graph.info(val)?;
+				info.check_arguments(&[]).map_err(|message| {
+					Error::script(format!(
+						"{} (required by {}): {}",
+						val.id, script.id, message
+					))
+				})?;
+				required.push((val, should_elevate(val, info, false)?));
+			}
+		}
+		let elevate = should_elevate(&script, &info, sudo)?;
+
+		// Run the scripts it requires first
+		for (val, elevate) in required {
+			println!(
+				"{} {} {}",
+				"==>".blue().bold(),
+				val.id.bold(),
+				format!("(required by {})", script.id).dimmed()
+			);
+			let timeout = timeout.or(graph.info(val)?.timeout);
+			check_outcome(val, self.run_script(val, &[], elevate, log, timeout)?)?;
+		}
+		let timeout = timeout.or(info.timeout);
+		check_outcome(
+			&script,
+			self.run_script(&script, args, elevate, log, timeout)?,
+		)
+	}
+
+	pub fn run_all(
+		&self,
+		keep_going: bool,
+		once: bool,
+		log: bool,
+		timeout: Option<Duration>,
+	) -> Result<()> {
+		let history = match once {
+			true => history::read_history(&history::get_history_path())?,
+			false => vec![],
+		};
+		let host = util::get_hostname();
+		let root = util::is_root();
+		let graph = self.get_graph();
+		// Scripts of other categories only run when required
+		let mut scripts = graph
+			.scripts()
+			.iter()
+			.filter(|script| self.is_in_category(script))
+			.map(|script| Ok((script, graph.info(script)?)))
+			.collect::<Result<Vec<_>>>()?;
+		// Scripts without an order run last. The sort is stable, so ties keep
+		// their alphabetical order
+		scripts.sort_by_key(|(script, info)| {
+			let order = script.order(info);
+			(order.is_none(), order)
+		});
+		// Then scripts that are required move before the scripts requiring them
+		let targets: Vec<&Script> = scripts.iter().map(|(script, _)| *script).collect();
+		let scripts = graph.run_order(&targets)?;
+
+		let mut rows = vec![];
+		let mut failed = 0;
+		// The script that was interrupted, after which nothing else runs
+		let mut interrupted: Option<&Script> = None;
+		// The scripts that did not run, or failed
+		let mut unsuccessful: Vec<&Script> = vec![];
+		for script in scripts {
+			let info = graph.info(script)?;
+			let missing = graph
+				.requirements(script)?
+				.into_iter()
+				.find(|required| unsuccessful.iter().any(|val| val.path == required.path));
+
+			let skipped = if interrupted.is_some() || (failed > 0 && !keep_going) {
+				Some(String::from("not run"))
+			} else if !info.supports_current_os() {
+				Some(format!("skipped (os: {})", info.os.join(",")))
+			} else if info.check_arguments(&[]).is_err() {
+				// Scripts run without arguments here
+				let usage: Vec<String> = info.arguments.iter().map(|arg| arg.to_string()).collect();
+				Some(format!("skipped (requires arguments: {})", usage.join(" ")))
+			} else {
+				missing.map(|required| format!("not run (requires {})", required.id))
+			};
+			if let Some(skipped) = skipped {
+				rows.push(vec![
+					script.id.clone(),
+					skipped.dimmed().to_string(),
+					String::new(),
+				]);
+				unsuccessful.push(script);
+				continue;
+			}
+			if once && already_ran(&history, &host, script)? {
+				rows.push(vec![
+					script.id.clone(),
+					"skipped (already ran)".dimmed().to_string(),
+					String::new(),
+				]);
+				continue;
+			}
+			let elevate = info.requires_sudo && !root;
+			if elevate && !confirm_sudo(script)? {
+				rows.push(vec![
+					script.id.clone(),
+					"skipped (requires root)".dimmed().to_string(),
+					String::new(),
+				]);
+				unsuccessful.push(script);
+				continue;
+			}
+
+			println!("{} {}", "==>".blue().bold(), script.id.bold());
+			let start = Instant::now();
+			let timeout = timeout.or(info.timeout);
+			let status = match self.run_script(script, &[], elevate, log, timeout) {
+				Ok(outcome) if outcome.success() => "ok".green().to_string(),
+				Ok(outcome) => {
+					failed += 1;
+					unsuccessful.push(script);
+					match outcome {
+						Outcome::Exited(status) => match status.code() {
+							Some(code) => format!("failed (exit code {})", code),
+							None => format!("failed ({})", status),
+						},
+						Outcome::TimedOut(timeout) => {
+							format!("timed out (after {})", format_duration(timeout))
+						}
+						Outcome::Interrupted => {
+							interrupted = Some(script);
+							String::from("interrupted")
+						}
+					}
+					.red()
+					.to_string()
+				}
+				Err(err) => {
+					failed += 1;
+					unsuccessful.push(script);
+					format!("error: {}", err).red().to_string()
+				}
+			};
+			rows.push(vec![
+				script.id.clone(),
+				status,
+				format!("{:.1}s", start.elapsed().as_secs_f32()),
+			]);
+		}
+
+		println!();
+		print_table(&["NAME", "STATUS", "TIME"], &rows);
+
+		match (interrupted, failed) {
+			(Some(script), _) => Err(Error::Interrupted {
+				script: script.id.clone(),
+			}),
+			(None, 0) => Ok(()),
+			(None, _) => Err(Error::ScriptsFailed { failed }),
+		}
+	}
+
+	pub fn new_script(&self, name: &str, dir: bool) -> Result<()> {
+		// A name like `repo/name` creates the script in that repository, and
+		// otherwise it goes in the main one
+		if self.category == ALL_CATEGORIES {
+			return Err(Error::script(format!(
+				"Choose the category to create {} in, not {}",
+				name, ALL_CATEGORIES
+			)));
+		}
+		let find_repo = |repo_name: &str| {
+			self
+				.category_dirs
+				.iter()
+				.find(|category_dir| category_dir.repo.name == repo_name)
+		};
+		let (
+			CategoryDir {
+				repo,
+				dir: category_dir,
+				..
+			},
+			name,
+		) = match name.split_once('/') {
+			Some((repo_name, rest)) if find_repo(repo_name).is_some() => {
+				(find_repo(repo_name).unwrap(), rest)
+			}
+			_ => (find_repo(MAIN_REPO_NAME).unwrap(), name),
+		};
+		let (stem, ext) = match name.rsplit_once('.') {
+			Some((stem, ext)) => (stem, ext),
+			None => (name, "sh"),
+		};
+		if stem.is_empty() || stem.contains('/') {
+			return Err(Error::script(format!("Invalid script name '{}'", name)));
+		}
+
+		let template = get_script_template(&repo.dotmgr_dir, ext)?.replace("{{name}}", stem);
+		let path = match dir {
+			true => category_dir.join(stem).join(format!("script.{}", ext)),
+			false => category_dir.join(format!("{}.{}", stem, ext)),
+		};
+		let script_path = match dir {
+			true => category_dir.join(stem),
+			false => path.clone(),
+		};
+		if script_path.exists() {
+			return Err(Error::script(format!(
+				"Script already exists: {}",
+				script_path.display()
+			)));
+		}
+
+		let parent = path.parent().unwrap();
+		fs::create_dir_all(parent).map_err(|err| Error::io(parent, err))?;
+		fs::write(&path, template).map_err(|err| Error::io(&path, err))?;
+		set_executable(&path)?;
+		println!("{} {}", "created:".dimmed(), path.display());
+
+		log::info!("editor: {}", self.config.editor);
+
+		open_with(&self.config.editor, &path)
+	}
+
+	pub fn lint(&self, query: Option<String>) -> Result<()> {
+		// Every match of an ambiguous query is checked, rather than choosing one
+		let scripts = match query {
+			Some(val) => match util::find_scripts(&self.category_dirs, &val)? {
+				ScriptMatch::One(script) => vec![script],
+				ScriptMatch::Many(scripts) => scripts,
+			},
+			None => util::get_scripts(&self.category_dirs),
+		};
+		let shellcheck = lint::has_shellcheck();
+		if !shellcheck {
+			eprintln!("warning: shellcheck is not installed, so only metadata is checked");
+		}
+
+		let mut errors = 0;
+		let mut warnings = 0;
+		let mut print_findings = |title: &str, findings: &[Finding]| {
+			if findings.is_empty() {
+				return;
+			}
+			println!("{}", title.bold());
+			for finding in findings {
+				let severity = match finding.severity {
+					Severity::Error => {
+						errors += 1;
+						finding.severity.to_string().red()
+					}
+					Severity::Warning => {
+						warnings += 1;
+						finding.severity.to_string().yellow()
+					}
+					Severity::Note => finding.severity.to_string().dimmed(),
+				};
+				match finding.line {
+					0 => println!("  {}: {}", severity, finding.message),
+					line => println!(
+						"  {}:{}: {}: {}",
+						line, finding.column, severity, finding.message
+					),
+				}
+			}
+		};
+
+		for script in &scripts {
+			let file = script.file();
+			let mut findings = vec![];
+			match script.info() {
+				Ok(_) => {}
+				Err(Error::Metadata { line, message, .. }) => findings.push(Finding {
+					line,
+					column: 1,
+					severity: Severity::Error,
+					message: format!("invalid metadata: {}", message),
+				}),
+				Err(err) => findings.push(Finding {
+					line: 0,
+					column: 0,
+					severity: Severity::Error,
+					message: err.to_string(),
+				}),
+			}
+			if shellcheck && lint::is_shell_script(&file) {
+				let sources = util::get_sources(&script.repo.dotmgr_dir, "sh");
+				findings.extend(lint::shellcheck(&file, &sources)?);
+			}
+			print_findings(&script.id, &findings);
+		}
+
+		// Requirements are checked across every script of the category.
+		// Invalid metadata was already reported with its script
+		let graph = self.get_graph();
+		let targets: Vec<&Script> = graph
+			.scripts()
+			.iter()
+			.filter(|script| self.is_in_category(script))
+			.collect();
+		match graph.run_order(&targets) {
+			Ok(_) | Err(Error::Metadata { .. }) => {}
+			Err(err) => {
+				let finding = Finding {
+					line: 0,
+					column: 0,
+					severity: Severity::Error,
+					message: err.to_string(),
+				};
+				print_findings("requires", &[finding]);
+			}
+		}
+
+		println!(
+			"Checked {} script(s): {} error(s), {} warning(s)",
+			scripts.len(),
+			errors,
+			warnings
+		);
+		match errors {
+			0 => Ok(()),
+			_ => Err(Error::Lint { errors }),
+		}
 	}
 
-	pub fn view(&self, glob_pattern: Option<String>) {
-		let script = util::get_script_exec(self.category_path.clone(), glob_pattern);
-		let pager = util::get_pager();
+	pub fn history(&self, query: Option<String>) -> Result<()> {
+		let mut runs = history::read_history(&history::get_history_path())?;
+		if query.is_some() {
+			let Some(script) = self.get_script_exec(query)? else {
+				return Ok(());
+			};
+			runs.retain(|run| run.path == script.path);
+		}
+
+		let rows: Vec<Vec<String>> = runs
+			.into_iter()
+			.map(|run| {
+				vec![
+					run.id,
+					format_timestamp(run.start),
+					format!("{}s", run.end.saturating_sub(run.start)),
+					run.host,
+					match (run.success, run.exit_code) {
+						(true, _) => "ok".green().to_string(),
+						(false, Some(code)) => format!("failed (exit code {})", code).red().to_string(),
+						(false, None) => "failed (killed)".red().to_string(),
+					},
+					run.hash,
+				]
+			})
+			.collect();
+		print_table(
+			&["NAME", "STARTED (UTC)", "TIME", "HOST", "STATUS", "HASH"],
+			&rows,
+		);
+
+		Ok(())
+	}
 
+	pub fn logs(&self, query: Option<String>) -> Result<()> {
+		let Some(script) = self.get_script_exec(query)? else {
+			return Ok(());
+		};
+		let log = history::find_latest_log(&script).ok_or_else(|| {
+			Error::script(format!(
+				"No logs of {}. Run it with --log to keep one",
+				script.id
+			))
+		})?;
+		let pager = self.config.pager();
 		log::info!("pager: {}", pager);
+		log::info!("log: {}", log.display());
 
-		Command::new(pager)
-			.arg(script)
-			.spawn()
-			.unwrap()
-			.wait()
-			.unwrap();
+		open_with(&pager, &log)
 	}
 
-	pub fn edit(&self, glob_pattern: Option<String>) {
-		let script = util::get_script_exec(self.category_path.clone(), glob_pattern);
-		let editor = util::get_editor();
+	pub fn graph(&self, dot: bool) -> Result<()> {
+		// The scripts of the category, with those they require from others
+		let graph = self.get_graph();
+		let targets: Vec<&Script> = graph
+			.scripts()
+			.iter()
+			.filter(|script| self.is_in_category(script))
+			.collect();
+		let graph = ScriptGraph::new(
+			graph
+				.with_requirements(&targets)?
+				.into_iter()
+				.cloned()
+				.collect(),
+		);
 
-		log::info!("editor: {}", editor);
+		match dot {
+			true => print!("{}", graph.to_dot()?),
+			false => print!("{}", graph.to_text()?),
+		}
 
-		Command::new(editor)
-			.args([script])
-			.spawn()
-			.unwrap()
-			.wait()
-			.unwrap();
+		Ok(())
 	}
 
-	pub fn run(&self, glob_pattern: Option<String>, sudo: bool) {
-		if sudo {
-			println!("{}", "Not implemented".italic());
-			exit(1);
+	/// Select a script by fuzzy matching its name, or with the TUI when there
+	/// is no query. The TUI then has a tab for each category, starting at the
+	/// current one. When the query matches several scripts, the TUI shows only
+	/// those. Returns `None` if the TUI was closed without choosing a script
+	fn get_script_exec(&self, query: Option<String>) -> Result<Option<Script>> {
+		let choose = |tabs: Vec<(String, Vec<Script>)>, selected: usize| match tui::choose_script(
+			tabs,
+			selected,
+			&self.config,
+		)? {
+			Some(val) => util::resolve_script(val).map(Some),
+			None => Ok(None),
+		};
+
+		let script = match query {
+			None => {
+				// Categories without scripts to run get no tab, unless they
+				// are the current one
+				let tabs: Vec<(String, Vec<Script>)> = category::get_categories(&self.config)
+					.into_iter()
+					.map(|category| {
+						let scripts: Vec<Script> = util::get_scripts(&category.dirs)
+							.into_iter()
+							.filter(|script| script.is_runnable())
+							.collect();
+						(category.name, scripts)
+					})
+					.filter(|(name, scripts)| !scripts.is_empty() || *name == self.category)
+					.collect();
+				let selected = tabs
+					.iter()
+					.position(|(name, _)| *name == self.category)
+					.unwrap_or(0);
+				choose(tabs, selected)?
+			}
+			Some(val) => match util::find_scripts(&self.category_dirs, &val)? {
+				ScriptMatch::One(script) => Some(script),
+				// The TUI needs a terminal, so list the matches instead
+				ScriptMatch::Many(scripts) if !io::stdin().is_terminal() => {
+					let ids: Vec<&str> = scripts.iter().map(|script| script.id.as_str()).collect();
+					return Err(Error::script(format!(
+						"'{}' matches more than one script: {}",
+						val,
+						ids.join(", ")
+					)));
+				}
+				ScriptMatch::Many(scripts) => {
+					choose(vec![(format!("Matching '{}'", val), scripts)], 0)?
+				}
+			},
+		};
+		let Some(script) = script else {
+			return Ok(None);
+		};
+
+		let dir = script.path.parent().unwrap();
+		env::set_current_dir(dir).map_err(|err| Error::io(dir, err))?;
+
+		Ok(Some(script))
+	}
+
+	/// Run a script with `args` and the implementation of the repository it is
+	/// from, as root if `sudo` is set, and record the run in the history. With
+	/// `log`, the output is also written to a log file. The script is stopped
+	/// if it runs longer than `timeout`
+	fn run_script(
+		&self,
+		script: &Script,
+		args: &[String],
+		sudo: bool,
+		log: bool,
+		timeout: Option<Duration>,
+	) -> Result<Outcome> {
+		// Scripts from the graph may still be directories
+		let script = &util::resolve_script(script.clone())?;
+		let hash = script.hash()?;
+		let start = SystemTime::now();
+		let log = match log {
+			true => {
+				let millis = start
+					.duration_since(UNIX_EPOCH)
+					.map_or(0, |duration| duration.as_millis());
+				Some(history::get_log_path(script, millis))
+			}
+			false => None,
+		};
+		let outcome = self.exec_script(script, args, sudo, log.as_deref(), timeout)?;
+
+		let seconds = |time: SystemTime| {
+			time
+				.duration_since(UNIX_EPOCH)
+				.map_or(0, |duration| duration.as_secs())
+		};
+		let run = ScriptRun {
+			id: script.id.clone(),
+			path: script.file(),
+			hash,
+			host: util::get_hostname(),
+			start: seconds(start),
+			end: seconds(SystemTime::now()),
+			success: outcome.success(),
+			exit_code: outcome.code(),
+		};
+		if let Err(err) = history::append_history(&history::get_history_path(), &run) {
+			eprintln!("warning: failed to record the run: {}", err);
 		}
 
-		let env = util::get_environment(&self.config).unwrap();
-		let entrypoint = util::get_entrypoint_sh(self.config.dotmgr_dir.to_str().unwrap().clone());
-		let script = util::get_script_exec(self.category_path.clone(), glob_pattern);
-		let sources = util::get_sources(&self.config.dotmgr_dir.to_str().unwrap().clone());
+		Ok(outcome)
+	}
+
+	fn exec_script(
+		&self,
+		script: &Script,
+		args: &[String],
+		sudo: bool,
+		log: Option<&Path>,
+		timeout: Option<Duration>,
+	) -> Result<Outcome> {
+		let dotmgr_dir = &script.repo.dotmgr_dir;
+		let env = util::get_environment(dotmgr_dir)?;
+		let runner = script.runner().ok_or_else(|| {
+			Error::script(format!(
+				"{} has no entrypoint for its extension, and no shebang",
+				script.id
+			))
+		})?;
 
 		for (key, value) in &env {
 			log::info!("env: {key}: {value}")
 		}
-		log::info!("entrypoint: {}", entrypoint.to_str().unwrap());
-		log::info!("script: {}", script.to_str().unwrap());
-		for source in sources.split(":") {
-			log::info!("source: {}", source);
+		// A script that is a directory runs its script file
+		let file = script.file();
+		log::info!("script: {}", file.display());
+
+		// The program to run, and the arguments that come before `args`
+		let (program, leading_args) = match runner {
+			Runner::Entrypoint(entrypoint) => {
+				// Scripts only have an entrypoint for a Unicode extension
+				let ext = file
+					.extension()
+					.and_then(|ext| ext.to_str())
+					.unwrap_or_default();
+				log::info!("entrypoint: {}", entrypoint.display());
+				let mut sources = OsString::new();
+				for (i, source) in util::get_sources(dotmgr_dir, ext).iter().enumerate() {
+					log::info!("source: {}", source.display());
+					if i > 0 {
+						sources.push(":");
+					}
+					sources.push(source);
+				}
+
+				let leading_args = vec![OsString::from(&file), sources];
+				(entrypoint.into_os_string(), leading_args)
+			}
+			Runner::Shebang(mut interpreter) => {
+				log::info!("shebang: {}", interpreter.join(" "));
+
+				let program = OsString::from(interpreter.remove(0));
+				let mut leading_args: Vec<OsString> =
+					interpreter.into_iter().map(OsString::from).collect();
+				leading_args.push(OsString::from(&file));
+				(program, leading_args)
+			}
+		};
+
+		let mut command = match sudo {
+			true => {
+				let sudo = util::get_sudo_command().ok_or_else(|| Error::Unsupported {
+					message: String::from("Running scripts as root needs sudo, doas, or pkexec"),
+				})?;
+				log::info!("sudo: {}", sudo.display());
+
+				// sudo and pkexec reset the environment, so it is set with env
+				let mut command = Command::new(sudo);
+				command
+					.arg("env")
+					.args(env.iter().map(|(key, value)| format!("{key}={value}")))
+					.arg(program);
+				command
+			}
+			false => {
+				let mut command = Command::new(program);
+				command.envs(env);
+				command
+			}
+		};
+
+		command
+			.args(leading_args)
+			.args(args)
+			.current_dir(file.parent().unwrap());
+
+		match log {
+			Some(log) => run_with_log(&mut command, log, timeout),
+			None => {
+				let mut child = process::spawn(&mut command)
+					.map_err(|err| Error::spawn(Path::new(command.get_program()), err))?;
+				process::wait(&mut child, timeout)
+					.map_err(|err| Error::spawn(Path::new(command.get_program()), err))
+			}
+		}
+	}
+}
+
+/// Get the template for new scripts with extension `ext`: `templates/script.<ext>`
+/// in the dotmgr directory, or else the built-in one for shell scripts
+fn get_script_template(dotmgr_dir: &Path, ext: &str) -> Result<String> {
+	let path = dotmgr_dir.join("templates").join(format!("script.{}", ext));
+	match fs::read_to_string(&path) {
+		Ok(val) => Ok(val),
+		Err(err) if err.kind() == io::ErrorKind::NotFound => match ext {
+			"sh" => Ok(String::from(SCRIPT_TEMPLATE)),
+			_ => Err(Error::script(format!(
+				"No template for .{} scripts. Create one at {}",
+				ext,
+				path.display()
+			))),
+		},
+		Err(err) => Err(Error::io(&path, err)),
+	}
+}
+
+/// Run a command, copying its stdout and stderr into the file at `log`, and
+/// stop it if it runs longer than `timeout`
+fn run_with_log(command: &mut Command, log: &Path, timeout: Option<Duration>) -> Result<Outcome> {
+	let dir = log.parent().unwrap();
+	fs::create_dir_all(dir).map_err(|err| Error::io(dir, err))?;
+	let file = File::create(log).map_err(|err| Error::io(log, err))?;
+	let file = Arc::new(Mutex::new(file));
+	log::info!("log: {}", log.display());
+
+	let mut child = process::spawn(command.stdout(Stdio::piped()).stderr(Stdio::piped()))
+		.map_err(|err| Error::spawn(Path::new(command.get_program()), err))?;
+
+	let stdout = child.stdout.take().unwrap();
+	let stderr = child.stderr.take().unwrap();
+	let threads = [
+		{
+			let file = file.clone();
+			thread::spawn(move || tee(stdout, io::stdout(), &file))
+		},
+		thread::spawn(move || tee(stderr, io::stderr(), &file)),
+	];
+
+	let outcome = process::wait(&mut child, timeout).map_err(|err| Error::io(log, err));
+	for thread in threads {
+		thread.join().unwrap();
+	}
+
+	outcome
+}
+
+/// Copy everything from `reader` to both `writer` and `file`. Failing to write
+/// does not stop the copy, so that the command does not block on a full pipe
+fn tee(mut reader: impl Read, mut writer: impl Write, file: &Mutex<File>) {
+	let mut buf = [0; 8192];
+	loop {
+		let n = match reader.read(&mut buf) {
+			Ok(0) => break,
+			Ok(n) => n,
+			Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+			Err(_) => break,
+		};
+		let _ = writer.write_all(&buf[..n]).and_then(|_| writer.flush());
+		let _ = file.lock().unwrap().write_all(&buf[..n]);
+	}
+}
+
+/// Fail if a script did not exit successfully
+fn check_outcome(script: &Script, outcome: Outcome) -> Result<()> {
+	match outcome {
+		Outcome::Exited(status) if status.success() => Ok(()),
+		Outcome::Exited(status) => Err(Error::ScriptExit {
+			script: script.id.clone(),
+			status,
+		}),
+		Outcome::TimedOut(timeout) => Err(Error::ScriptTimeout {
+			script: script.id.clone(),
+			timeout,
+		}),
+		Outcome::Interrupted => Err(Error::Interrupted {
+			script: script.id.clone(),
+		}),
+	}
+}
+
+/// Fail if a program exited unsuccessfully
+/// Open `path` with a command line like the editor or pager, whose first word
+/// is the program and the rest its leading arguments, like `code --wait`
+fn open_with(command_line: &str, path: &Path) -> Result<()> {
+	let mut words = command_line.split_whitespace();
+	let program = words.next().unwrap_or_default();
+
+	let status = Command::new(program)
+		.args(words)
+		.arg(path)
+		.status()
+		.map_err(|err| Error::spawn(Path::new(program), err))?;
+
+	check_status(program, status)
+}
+
+fn check_status(program: &str, status: ExitStatus) -> Result<()> {
+	match status.success() {
+		true => Ok(()),
+		false => Err(Error::CommandFailed {
+			command: PathBuf::from(program),
+			status,
+			stderr: String::new(),
+		}),
+	}
+}
+
+/// Whether to run a script as root: when asked to with `sudo`, or when its
+/// metadata requires it and the user agrees. Fails if the user does not agree
+fn should_elevate(script: &Script, info: &ScriptInfo, sudo: bool) -> Result<bool> {
+	if util::is_root() {
+		return Ok(false);
+	}
+	if sudo {
+		return Ok(true);
+	}
+
+	match info.requires_sudo {
+		true if confirm_sudo(script)? => Ok(true),
+		true => Err(Error::script(format!(
+			"Not running {}, which requires root",
+			script.id
+		))),
+		false => Ok(false),
+	}
+}
+
+/// Ask whether to run a script that requires root as root
+fn confirm_sudo(script: &Script) -> Result<bool> {
+	prompt(&format!("{} requires root. Run it as root?", script.id))
+}
+
+/// Whether the script already ran successfully on `host`, with the content it
+/// has now
+fn already_ran(history: &[ScriptRun], host: &str, script: &Script) -> Result<bool> {
+	Ok(history::has_succeeded(
+		history,
+		&script.file(),
+		&script.hash()?,
+		host,
+	))
+}
+
+/// Format seconds since the Unix epoch as a UTC date and time
+fn format_timestamp(seconds: u64) -> String {
+	let days = (seconds / 86400) as i64;
+	let time = seconds % 86400;
+
+	// The days to civil date algorithm of Howard Hinnant
+	let z = days + 719468;
+	let era = z.div_euclid(146097);
+	let day_of_era = z.rem_euclid(146097);
+	let year_of_era =
+		(day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let mp = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * mp + 2) / 5 + 1;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 };
+	let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+	format!(
+		"{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+		year,
+		month,
+		day,
+		time / 3600,
+		time % 3600 / 60,
+		time % 60
+	)
+}
+
+/// The width of a cell on the terminal, ignoring color escape sequences
+fn visible_width(cell: &str) -> usize {
+	let mut width = 0;
+	let mut chars = cell.chars();
+	while let Some(c) = chars.next() {
+		if c == '\x1b' {
+			chars.by_ref().find(|c| *c == 'm');
+		} else {
+			width += 1;
+		}
+	}
+	width
+}
+
+/// Print rows as a table with aligned columns. Cells may be colored
+fn print_table(header: &[&str], rows: &[Vec<String>]) {
+	let mut widths: Vec<usize> = header.iter().map(|cell| cell.len()).collect();
+	for row in rows {
+		for (i, cell) in row.iter().enumerate() {
+			widths[i] = widths[i].max(visible_width(cell));
+		}
+	}
+
+	let print_row = |cells: Vec<String>| {
+		let mut line = String::new();
+		for (i, cell) in cells.iter().enumerate() {
+			line.push_str(cell);
+			if i + 1 < cells.len() {
+				line.push_str(&" ".repeat(widths[i] - visible_width(cell) + 2));
+			}
 		}
+		println!("{}", line.trim_end());
+	};
 
-		Command::new(entrypoint)
-			.arg(script)
-			.arg(sources)
-			.envs(env)
-			.spawn()
-			.unwrap()
-			.wait()
-			.unwrap();
+	print_row(header.iter().map(|cell| cell.bold().to_string()).collect());
+	for row in rows {
+		print_row(row.clone());
 	}
 }
@@ -1,46 +1,718 @@
-use std::{env, fmt, path::PathBuf};
+//! Configuration, resolved from flags, environment variables, and config
+//! files
 
+use std::{
+	collections::{BTreeMap, HashMap},
+	env, fmt, fs, io,
+	path::{Path, PathBuf},
+	str::FromStr,
+};
+
+use serde::Deserialize;
+
+use crate::{
+	error::{self, Error},
+	util,
+};
+
+/// The resolved configuration. Create it with `Config::load`
+#[derive(Clone)]
 pub struct Config {
+	/// The dotfiles repository, `~/.dotfiles` by default
 	pub dotfiles_dir: PathBuf,
+	/// The directory for the current operating system in `dotfiles_dir`
 	pub os_dir: PathBuf,
+	/// The directory with `impl`, `util`, and the scripts, in `os_dir`
 	pub dotmgr_dir: PathBuf,
+	/// What to do when a target already exists
+	pub conflict_policy: ConflictPolicy,
+	/// The command line to edit scripts with
+	pub editor: String,
+	/// The command line to view scripts with, if one is set. See `pager()`
+	pub pager: Option<String>,
+	/// Category names mapped to their script directories
+	pub categories: BTreeMap<String, PathBuf>,
+	/// Every repository, in order of increasing precedence. The last one is
+	/// the repository at dotfiles_dir
+	pub repos: Vec<DotfilesRepo>,
+	/// Where each value came from
+	pub origins: HashMap<String, Origin>,
 }
 
-impl Default for Config {
-	fn default() -> Config {
-		let dotfiles_dir = match env::var("DOTMGR_DOTFILES_DIR") {
-			Ok(val) => {
-				let p = PathBuf::from(val);
-				if p.is_absolute() {
-					p
-				} else {
-					PathBuf::from(dirs::home_dir().unwrap()).join(".dotfiles")
-				}
-			}
-			Err(_) => PathBuf::from(dirs::home_dir().unwrap()).join(".dotfiles"),
+/// What to do when a dotfile would be deployed onto an existing file or
+/// directory that is not a symlink
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+	/// Leave the target alone
+	Skip,
+	/// Rename the target to `<target>.bak`, then deploy
+	Backup,
+	/// Remove the target, then deploy
+	Overwrite,
+}
+
+impl fmt::Display for ConflictPolicy {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ConflictPolicy::Skip => write!(f, "skip"),
+			ConflictPolicy::Backup => write!(f, "backup"),
+			ConflictPolicy::Overwrite => write!(f, "overwrite"),
+		}
+	}
+}
+
+impl FromStr for ConflictPolicy {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"skip" => Ok(ConflictPolicy::Skip),
+			"backup" => Ok(ConflictPolicy::Backup),
+			"overwrite" => Ok(ConflictPolicy::Overwrite),
+			_ => Err(format!(
+				"unknown conflict policy '{}', expected 'skip', 'backup', or 'overwrite'",
+				s
+			)),
+		}
+	}
+}
+
+/// Where a configuration value came from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Origin {
+	/// The built in default
+	Default,
+	/// A config file
+	File(PathBuf),
+	/// An environment variable
+	Env(String),
+	/// A command line flag
+	Cli(String),
+}
+
+impl fmt::Display for Origin {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Origin::Default => write!(f, "default"),
+			Origin::File(path) => write!(f, "file {}", path.display()),
+			Origin::Env(name) => write!(f, "env {}", name),
+			Origin::Cli(flag) => write!(f, "flag {}", flag),
+		}
+	}
+}
+
+/// A dotfiles repository, with its own dotmgr directory
+#[derive(Clone)]
+pub struct DotfilesRepo {
+	/// The name of the repository, `main` for the one at `Config::dotfiles_dir`
+	pub name: String,
+	/// Like `Config::dotfiles_dir`
+	pub dotfiles_dir: PathBuf,
+	/// Like `Config::os_dir`
+	pub os_dir: PathBuf,
+	/// Like `Config::dotmgr_dir`
+	pub dotmgr_dir: PathBuf,
+}
+
+/// The name of the repository at `Config::dotfiles_dir`
+pub const MAIN_REPO_NAME: &str = "main";
+
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RepoFile {
+	name: String,
+	dotfiles_dir: PathBuf,
+	os_dir: Option<PathBuf>,
+	dotmgr_dir: Option<PathBuf>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+	dotfiles_dir: Option<PathBuf>,
+	os_dir: Option<PathBuf>,
+	dotmgr_dir: Option<PathBuf>,
+	conflict_policy: Option<ConflictPolicy>,
+	editor: Option<String>,
+	pager: Option<String>,
+	#[serde(default)]
+	categories: BTreeMap<String, PathBuf>,
+	repos: Option<Vec<RepoFile>>,
+}
+
+impl ConfigFile {
+	/// Read and parse a config file, or `None` if it does not exist
+	fn read(path: &Path) -> error::Result<Option<ConfigFile>> {
+		let content = match fs::read_to_string(path) {
+			Ok(val) => val,
+			Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+			Err(err) => return Err(Error::io(path, err)),
 		};
 
-		let os_dir = match env::consts::OS {
-			"windows" => dotfiles_dir.clone().join("os/windows"),
-			_ => dotfiles_dir.clone().join("os/unix"),
+		toml::from_str(&content)
+			.map(Some)
+			.map_err(|err| Error::Config {
+				origin: Origin::File(path.to_path_buf()).to_string(),
+				message: err.to_string().trim_end().to_string(),
+			})
+	}
+}
+
+/// Get the user config file, `$XDG_CONFIG_HOME/dotfile/config.toml`
+pub fn get_user_config_file() -> Option<PathBuf> {
+	dirs::config_dir().map(|dir| dir.join("dotfile/config.toml"))
+}
+
+/// Get the config file that lives in the dotfiles repository
+pub fn get_repo_config_file(dotfiles_dir: &Path) -> PathBuf {
+	dotfiles_dir.join(".dotfile.toml")
+}
+
+fn get_default_os_dir(dotfiles_dir: &Path) -> PathBuf {
+	match env::consts::OS {
+		"windows" => dotfiles_dir.join("os/windows"),
+		_ => dotfiles_dir.join("os/unix"),
+	}
+}
+
+fn expand_home(home_dir: &Path, path: &Path) -> PathBuf {
+	match path.strip_prefix("~") {
+		Ok(rest) => home_dir.join(rest),
+		Err(_) => path.to_path_buf(),
+	}
+}
+
+/// Values given on the command line, which take precedence over everything
+/// else
+#[derive(Default)]
+pub struct ConfigOverrides {
+	pub dotfiles_dir: Option<PathBuf>,
+	pub os_dir: Option<PathBuf>,
+	pub dotmgr_dir: Option<PathBuf>,
+	pub conflict_policy: Option<ConflictPolicy>,
+	pub editor: Option<String>,
+	pub pager: Option<String>,
+}
+
+/// Resolves each value by precedence: command line flag, then environment
+/// variable, then the user config file, then the repository config file,
+/// then the default
+struct Resolver {
+	files: Vec<(PathBuf, ConfigFile)>,
+	// The environment variables
+	vars: HashMap<String, String>,
+	origins: HashMap<String, Origin>,
+}
+
+impl Resolver {
+	fn resolve<T>(
+		&mut self,
+		key: &str,
+		cli: Option<T>,
+		env_var: &str,
+		from_env: impl Fn(String) -> Option<T>,
+		from_file: impl Fn(&ConfigFile) -> Option<T>,
+		default: impl FnOnce() -> T,
+	) -> T {
+		if let Some(val) = cli {
+			self.origins.insert(
+				String::from(key),
+				Origin::Cli(format!("--{}", key.replace('_', "-"))),
+			);
+			return val;
+		}
+
+		if let Some(val) = self.vars.get(env_var).cloned().and_then(from_env) {
+			self
+				.origins
+				.insert(String::from(key), Origin::Env(String::from(env_var)));
+			return val;
+		}
+
+		for (path, file) in &self.files {
+			if let Some(val) = from_file(file) {
+				self
+					.origins
+					.insert(String::from(key), Origin::File(path.clone()));
+				return val;
+			}
+		}
+
+		self.origins.insert(String::from(key), Origin::Default);
+		default()
+	}
+
+	/// Directories given explicitly must exist, since a typo would otherwise
+	/// silently point at the wrong place
+	fn check_dir(&self, key: &str, dir: &Path) -> error::Result<()> {
+		match self.origins.get(key) {
+			Some(origin @ (Origin::Cli(_) | Origin::Env(_))) if !dir.is_dir() => Err(Error::Config {
+				origin: origin.to_string(),
+				message: format!("not a directory: {}", dir.display()),
+			}),
+			_ => Ok(()),
+		}
+	}
+}
+
+impl Config {
+	/// Resolve every value from `overrides`, the environment, and the config
+	/// files
+	pub fn load(overrides: ConfigOverrides) -> error::Result<Config> {
+		let home_dir = dirs::home_dir().ok_or_else(|| Error::Config {
+			origin: Origin::Default.to_string(),
+			message: String::from("could not determine the home directory"),
+		})?;
+		let cwd = env::current_dir().map_err(|err| Error::io(Path::new("."), err))?;
+
+		let mut resolver = Resolver {
+			files: vec![],
+			// Variables that are not Unicode are ignored, like unset ones
+			vars: env::vars_os()
+				.filter_map(|(key, val)| Some((key.into_string().ok()?, val.into_string().ok()?)))
+				.collect(),
+			origins: HashMap::new(),
 		};
+		if let Some(path) = get_user_config_file() {
+			if let Some(file) = ConfigFile::read(&path)? {
+				resolver.files.push((path, file));
+			}
+		}
 
-		Config {
-			dotfiles_dir,
+		Config::resolve(overrides, resolver, &home_dir, &cwd, ConfigFile::read)
+	}
+
+	/// Resolve every value with `resolver`, which has the environment and the
+	/// user config file. The repository config file is read with `read_file`
+	fn resolve(
+		overrides: ConfigOverrides,
+		mut resolver: Resolver,
+		home_dir: &Path,
+		cwd: &Path,
+		read_file: impl Fn(&Path) -> error::Result<Option<ConfigFile>>,
+	) -> error::Result<Config> {
+		let expand = |path: &Path| expand_home(home_dir, path);
+		let resolve_cwd = |path: &Path| cwd.join(expand(path));
+
+		let dotfiles_dir = resolver.resolve(
+			"dotfiles_dir",
+			overrides.dotfiles_dir.map(|p| resolve_cwd(&p)),
+			"DOTMGR_DOTFILES_DIR",
+			|val| Some(resolve_cwd(Path::new(&val))),
+			|file| file.dotfiles_dir.as_ref().map(|p| home_dir.join(expand(p))),
+			|| home_dir.join(".dotfiles"),
+		);
+		resolver.check_dir("dotfiles_dir", &dotfiles_dir)?;
+
+		// The repository config file can set everything but the location of
+		// the repository itself
+		let repo_config_file = get_repo_config_file(&dotfiles_dir);
+		if let Some(file) = read_file(&repo_config_file)? {
+			resolver.files.push((repo_config_file, file));
+		}
+
+		let os_dir = resolver.resolve(
+			"os_dir",
+			overrides.os_dir.map(|p| resolve_cwd(&p)),
+			"DOTMGR_OS_DIR",
+			|val| Some(resolve_cwd(Path::new(&val))),
+			|file| file.os_dir.as_ref().map(|p| dotfiles_dir.join(expand(p))),
+			|| get_default_os_dir(&dotfiles_dir),
+		);
+
+		let dotmgr_dir = resolver.resolve(
+			"dotmgr_dir",
+			overrides.dotmgr_dir.map(|p| resolve_cwd(&p)),
+			"DOTMGR_DOTMGR_DIR",
+			|val| Some(resolve_cwd(Path::new(&val))),
+			|file| file.dotmgr_dir.as_ref().map(|p| os_dir.join(expand(p))),
+			|| os_dir.join("dotmgr"),
+		);
+		resolver.check_dir("os_dir", &os_dir)?;
+		resolver.check_dir("dotmgr_dir", &dotmgr_dir)?;
+
+		// An invalid value fails like one in a config file
+		if let Some(val) = resolver.vars.get("DOTMGR_CONFLICT_POLICY") {
+			val.parse::<ConflictPolicy>()
+				.map_err(|message| Error::Config {
+					origin: Origin::Env(String::from("DOTMGR_CONFLICT_POLICY")).to_string(),
+					message,
+				})?;
+		}
+		let conflict_policy = resolver.resolve(
+			"conflict_policy",
+			overrides.conflict_policy,
+			"DOTMGR_CONFLICT_POLICY",
+			|val| val.parse().ok(),
+			|file| file.conflict_policy,
+			|| ConflictPolicy::Skip,
+		);
+
+		let editor = resolver.resolve(
+			"editor",
+			overrides.editor,
+			"DOTMGR_EDITOR",
+			Some,
+			|file| file.editor.clone(),
+			util::get_editor,
+		);
+
+		// The default pager is only looked for when it is used
+		let pager = resolver.resolve(
+			"pager",
+			overrides.pager.map(Some),
+			"DOTMGR_PAGER",
+			|val| Some(Some(val)),
+			|file| file.pager.clone().map(Some),
+			|| None,
+		);
+
+		// Categories are merged, with earlier files taking precedence
+		let mut categories = BTreeMap::new();
+		for (path, file) in resolver.files.iter().rev() {
+			for (name, dir) in &file.categories {
+				categories.insert(name.clone(), dotmgr_dir.join(expand(dir)));
+				resolver
+					.origins
+					.insert(format!("categories.{}", name), Origin::File(path.clone()));
+			}
+		}
+
+		// Repositories layered beneath the main one, taken from the first file
+		// that has any
+		let mut repos: Vec<DotfilesRepo> = vec![];
+		if let Some((path, repo_files)) = resolver
+			.files
+			.iter()
+			.find_map(|(path, file)| file.repos.as_ref().map(|repos| (path, repos)))
+		{
+			for repo_file in repo_files {
+				if repo_file.name == MAIN_REPO_NAME
+					|| repos.iter().any(|repo| repo.name == repo_file.name)
+				{
+					return Err(Error::Config {
+						origin: Origin::File(path.clone()).to_string(),
+						message: format!("repository name is used more than once: {}", repo_file.name),
+					});
+				}
+
+				let dotfiles_dir = home_dir.join(expand(&repo_file.dotfiles_dir));
+				let os_dir = match &repo_file.os_dir {
+					Some(val) => dotfiles_dir.join(expand(val)),
+					None => get_default_os_dir(&dotfiles_dir),
+				};
+				let dotmgr_dir = match &repo_file.dotmgr_dir {
+					Some(val) => os_dir.join(expand(val)),
+					None => os_dir.join("dotmgr"),
+				};
+
+				resolver.origins.insert(
+					format!("repos.{}", repo_file.name),
+					Origin::File(path.clone()),
+				);
+				repos.push(DotfilesRepo {
+					name: repo_file.name.clone(),
+					dotfiles_dir,
+					os_dir,
+					dotmgr_dir,
+				});
+			}
+		}
+		repos.push(DotfilesRepo {
+			name: String::from(MAIN_REPO_NAME),
+			dotfiles_dir: dotfiles_dir.clone(),
 			os_dir: os_dir.clone(),
-			dotmgr_dir: os_dir.join("dotmgr"),
+			dotmgr_dir: dotmgr_dir.clone(),
+		});
+
+		Ok(Config {
+			dotfiles_dir,
+			os_dir,
+			dotmgr_dir,
+			conflict_policy,
+			editor,
+			pager,
+			categories,
+			repos,
+			origins: resolver.origins,
+		})
+	}
+
+	/// Get the command line to view scripts with: the one that is set, or else
+	/// the default pager
+	pub fn pager(&self) -> String {
+		self.pager.clone().unwrap_or_else(util::get_pager)
+	}
+
+	/// Get every value as a string, along with where it came from
+	pub fn values(&self) -> Vec<(String, String, Origin)> {
+		let origin = |key: &str| self.origins.get(key).cloned().unwrap_or(Origin::Default);
+
+		let mut values = vec![
+			(
+				String::from("dotfiles_dir"),
+				self.dotfiles_dir.display().to_string(),
+				origin("dotfiles_dir"),
+			),
+			(
+				String::from("os_dir"),
+				self.os_dir.display().to_string(),
+				origin("os_dir"),
+			),
+			(
+				String::from("dotmgr_dir"),
+				self.dotmgr_dir.display().to_string(),
+				origin("dotmgr_dir"),
+			),
+			(
+				String::from("conflict_policy"),
+				self.conflict_policy.to_string(),
+				origin("conflict_policy"),
+			),
+			(
+				String::from("editor"),
+				self.editor.clone(),
+				origin("editor"),
+			),
+			(String::from("pager"), self.pager(), origin("pager")),
+		];
+		for (name, dir) in &self.categories {
+			let key = format!("categories.{}", name);
+			values.push((key.clone(), dir.display().to_string(), origin(&key)));
+		}
+		for repo in &self.repos {
+			if repo.name == MAIN_REPO_NAME {
+				continue;
+			}
+
+			let key = format!("repos.{}", repo.name);
+			values.push((
+				key.clone(),
+				repo.dotmgr_dir.display().to_string(),
+				origin(&key),
+			));
 		}
+
+		values
 	}
 }
 
 impl fmt::Display for Config {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		write!(
-			f,
-			"dotfiles_dir: {}\nos_dir: {},\ndotmgr_dir: {}\n",
-			self.dotfiles_dir.to_str().unwrap(),
-			self.os_dir.to_str().unwrap(),
-			self.dotmgr_dir.to_str().unwrap()
+		for (key, value, _) in self.values() {
+			writeln!(f, "{}: {}", key, value)?;
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		collections::HashMap,
+		fs,
+		path::{Path, PathBuf},
+	};
+
+	use super::{Config, ConfigFile, ConfigOverrides, ConflictPolicy, Origin, Resolver};
+	use crate::test_util::TempDir;
+
+	/// Resolve the configuration with `home` as the home directory and
+	/// `home/cwd` as the current directory, the environment variables `vars`,
+	/// and the user and repository config files `user` and `repo`
+	fn try_resolve(
+		home: &Path,
+		overrides: ConfigOverrides,
+		vars: &[(&str, &str)],
+		user: Option<&str>,
+		repo: Option<&str>,
+	) -> crate::error::Result<Config> {
+		let user_file = home.join(".config/dotfile/config.toml");
+		let resolver = Resolver {
+			files: user
+				.map(|content| (user_file, toml::from_str(content).unwrap()))
+				.into_iter()
+				.collect(),
+			vars: vars
+				.iter()
+				.map(|(key, val)| (String::from(*key), String::from(*val)))
+				.collect(),
+			origins: HashMap::new(),
+		};
+		let read_file = |path: &Path| -> crate::error::Result<Option<ConfigFile>> {
+			assert!(path.ends_with(".dotfile.toml"));
+			Ok(repo.map(|content| toml::from_str(content).unwrap()))
+		};
+
+		Config::resolve(overrides, resolver, home, &home.join("cwd"), read_file)
+	}
+
+	/// Like `try_resolve`, for a configuration that is valid
+	fn resolve(
+		home: &Path,
+		overrides: ConfigOverrides,
+		vars: &[(&str, &str)],
+		user: Option<&str>,
+		repo: Option<&str>,
+	) -> Config {
+		try_resolve(home, overrides, vars, user, repo).unwrap()
+	}
+
+	#[test]
+	fn it_resolves_values_by_precedence() {
+		let home = TempDir::new("config");
+		fs::create_dir_all(home.join("cwd/branch")).unwrap();
+		let user_file = home.join(".config/dotfile/config.toml");
+		let repo_file = home.join(".dotfiles/.dotfile.toml");
+		let user = Some("editor = 'user'\npager = 'user'\nconflict_policy = 'backup'");
+		let repo = Some("editor = 'repo'\npager = 'repo'\nconflict_policy = 'overwrite'");
+		let vars = [("DOTMGR_EDITOR", "env"), ("DOTMGR_CONFLICT_POLICY", "skip")];
+
+		let config = resolve(
+			&home,
+			ConfigOverrides {
+				editor: Some(String::from("flag")),
+				..Default::default()
+			},
+			&vars,
+			user,
+			repo,
+		);
+		assert_eq!(config.editor, "flag");
+		assert_eq!(
+			config.origins["editor"],
+			Origin::Cli(String::from("--editor"))
+		);
+		assert_eq!(config.pager(), "user");
+		assert_eq!(config.origins["pager"], Origin::File(user_file.clone()));
+		assert_eq!(config.conflict_policy, ConflictPolicy::Skip);
+		assert_eq!(
+			config.origins["conflict_policy"],
+			Origin::Env(String::from("DOTMGR_CONFLICT_POLICY"))
+		);
+
+		let config = resolve(&home, ConfigOverrides::default(), &vars, user, repo);
+		assert_eq!(config.editor, "env");
+		assert_eq!(
+			config.origins["editor"],
+			Origin::Env(String::from("DOTMGR_EDITOR"))
+		);
+
+		let config = resolve(&home, ConfigOverrides::default(), &[], None, repo);
+		assert_eq!(config.editor, "repo");
+		assert_eq!(config.origins["editor"], Origin::File(repo_file));
+		assert_eq!(config.conflict_policy, ConflictPolicy::Overwrite);
+
+		let config = resolve(&home, ConfigOverrides::default(), &[], None, None);
+		assert_eq!(config.conflict_policy, ConflictPolicy::Skip);
+		assert_eq!(config.origins["conflict_policy"], Origin::Default);
+		assert_eq!(config.dotfiles_dir, home.join(".dotfiles"));
+		assert_eq!(config.origins["dotfiles_dir"], Origin::Default);
+
+		// An invalid value in the environment fails, like one in a file
+		let vars = [("DOTMGR_CONFLICT_POLICY", "nope")];
+		let err = try_resolve(&home, ConfigOverrides::default(), &vars, user, None)
+			.err()
+			.unwrap();
+		assert!(err.to_string().contains("DOTMGR_CONFLICT_POLICY"), "{err}");
+
+		// The default pager is only looked for when it is used
+		let config = resolve(&home, ConfigOverrides::default(), &[], None, None);
+		assert_eq!(config.pager, None);
+		assert_eq!(config.origins["pager"], Origin::Default);
+	}
+
+	#[test]
+	fn it_expands_paths() {
+		let home = TempDir::new("config");
+		fs::create_dir_all(home.join("cwd/branch")).unwrap();
+
+		// Flags and variables are relative to the current directory
+		let config = resolve(
+			&home,
+			ConfigOverrides {
+				dotfiles_dir: Some(PathBuf::from("branch")),
+				..Default::default()
+			},
+			&[],
+			None,
+			None,
+		);
+		assert_eq!(config.dotfiles_dir, home.join("cwd/branch"));
+		assert_eq!(config.os_dir, home.join("cwd/branch/os/unix"));
+		assert_eq!(config.dotmgr_dir, home.join("cwd/branch/os/unix/dotmgr"));
+		let vars = [("DOTMGR_DOTFILES_DIR", "~/cwd/branch")];
+		let config = resolve(&home, ConfigOverrides::default(), &vars, None, None);
+		assert_eq!(config.dotfiles_dir, home.join("cwd/branch"));
+
+		// In files, dotfiles_dir is relative to the home directory, os_dir to
+		// dotfiles_dir, and dotmgr_dir to os_dir
+		let user = "dotfiles_dir = 'dots'\nos_dir = 'linux'\ndotmgr_dir = 'mgr'";
+		let config = resolve(&home, ConfigOverrides::default(), &[], Some(user), None);
+		assert_eq!(config.dotfiles_dir, home.join("dots"));
+		assert_eq!(config.os_dir, home.join("dots/linux"));
+		assert_eq!(config.dotmgr_dir, home.join("dots/linux/mgr"));
+		let user = "dotfiles_dir = '~/dots'\nos_dir = '/etc/dots'\ndotmgr_dir = '~/mgr'";
+		let config = resolve(&home, ConfigOverrides::default(), &[], Some(user), None);
+		assert_eq!(config.dotfiles_dir, home.join("dots"));
+		assert_eq!(config.os_dir, PathBuf::from("/etc/dots"));
+		assert_eq!(config.dotmgr_dir, home.join("mgr"));
+
+		// Directories given explicitly must exist
+		let resolver = Resolver {
+			files: vec![],
+			vars: HashMap::from([(String::from("DOTMGR_OS_DIR"), String::from("missing"))]),
+			origins: HashMap::new(),
+		};
+		let err = Config::resolve(
+			ConfigOverrides::default(),
+			resolver,
+			&home,
+			&home.join("cwd"),
+			|_| Ok(None),
 		)
+		.err()
+		.unwrap();
+		assert_eq!(
+			err.to_string(),
+			format!(
+				"env DOTMGR_OS_DIR: not a directory: {}",
+				home.join("cwd/missing").display()
+			)
+		);
+	}
+
+	#[test]
+	fn it_merges_categories() {
+		let home = TempDir::new("config");
+		let user_file = home.join(".config/dotfile/config.toml");
+		let repo_file = home.join(".dotfiles/.dotfile.toml");
+
+		let user = "[categories]\nwork = 'work'\ngames = '~/games'";
+		let repo = "[categories]\nwork = 'scripts-job'\nfonts = 'fonts'";
+		let config = resolve(
+			&home,
+			ConfigOverrides::default(),
+			&[],
+			Some(user),
+			Some(repo),
+		);
+		let dotmgr_dir = home.join(".dotfiles/os/unix/dotmgr");
+		assert_eq!(
+			config.categories.into_iter().collect::<Vec<_>>(),
+			vec![
+				(String::from("fonts"), dotmgr_dir.join("fonts")),
+				(String::from("games"), home.join("games")),
+				(String::from("work"), dotmgr_dir.join("work")),
+			]
+		);
+		assert_eq!(
+			config.origins["categories.work"],
+			Origin::File(user_file.clone())
+		);
+		assert_eq!(config.origins["categories.games"], Origin::File(user_file));
+		assert_eq!(config.origins["categories.fonts"], Origin::File(repo_file));
 	}
 }
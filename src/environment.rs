@@ -0,0 +1,340 @@
+//! The output of `impl/environment.sh`
+//!
+//! `environment.sh` prints the variables that every script runs with, one
+//! assignment per line, quoted like in a shell:
+//!
+//! ```sh
+//! XDG_CONFIG_HOME=/home/fox/.config
+//! export EDITOR='nvim -p'
+//! GREETING="say \"woof\""
+//! # Comments and blank lines are ignored
+//! MOTD='first line
+//! second line'
+//! ```
+//!
+//! Unquoted backslashes escape the next character. Single quotes keep
+//! everything up to the next single quote, and double quotes keep everything
+//! up to the next double quote, where backslashes only escape `$`, `` ` ``,
+//! `"`, `\`, and newlines. Nothing is expanded, so `$HOME` stays as it is.
+//! Output with NUL characters, like that of `env -0`, is instead read as
+//! `KEY=VALUE` entries separated by NULs, without quoting
+
+/// A problem with the output of `environment.sh`
+#[derive(Debug, PartialEq, Eq)]
+pub struct EnvironmentError {
+	/// The line number, starting at 1
+	pub line: usize,
+	/// What is wrong with the line
+	pub message: String,
+}
+
+/// Parse the output of `environment.sh` into variables, in the order they
+/// were printed
+pub fn parse(output: &str) -> Result<Vec<(String, String)>, EnvironmentError> {
+	match output.contains('\0') {
+		true => parse_nul_separated(output),
+		false => Parser::new(output).parse(),
+	}
+}
+
+fn parse_nul_separated(output: &str) -> Result<Vec<(String, String)>, EnvironmentError> {
+	let mut vars = vec![];
+	let mut line = 1;
+	for entry in output.split('\0') {
+		if !entry.trim().is_empty() {
+			let (key, value) = entry.split_once('=').ok_or_else(|| EnvironmentError {
+				line,
+				message: format!("expected KEY=VALUE, not '{}'", entry.trim()),
+			})?;
+			check_key(key.trim_start(), line)?;
+			vars.push((String::from(key.trim_start()), String::from(value)));
+		}
+		line += entry.matches('\n').count();
+	}
+
+	Ok(vars)
+}
+
+/// Whether `key` can be the name of an environment variable
+fn check_key(key: &str, line: usize) -> Result<(), EnvironmentError> {
+	let mut chars = key.chars();
+	let valid = chars
+		.next()
+		.is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+		&& chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+	match valid {
+		true => Ok(()),
+		false => Err(EnvironmentError {
+			line,
+			message: format!("invalid variable name '{}'", key),
+		}),
+	}
+}
+
+struct Parser {
+	chars: Vec<char>,
+	pos: usize,
+	line: usize,
+}
+
+impl Parser {
+	fn new(output: &str) -> Parser {
+		Parser {
+			chars: output.chars().collect(),
+			pos: 0,
+			line: 1,
+		}
+	}
+
+	fn peek(&self) -> Option<char> {
+		self.chars.get(self.pos).copied()
+	}
+
+	fn next(&mut self) -> Option<char> {
+		let c = self.peek()?;
+		self.pos += 1;
+		if c == '\n' {
+			self.line += 1;
+		}
+		Some(c)
+	}
+
+	fn error(&self, line: usize, message: String) -> EnvironmentError {
+		EnvironmentError { line, message }
+	}
+
+	/// Skip spaces and tabs, but not newlines
+	fn skip_blanks(&mut self) {
+		while self.peek().is_some_and(|c| c == ' ' || c == '\t') {
+			self.next();
+		}
+	}
+
+	/// Skip to the end of the line, leaving the newline
+	fn skip_comment(&mut self) {
+		while self.peek().is_some_and(|c| c != '\n') {
+			self.next();
+		}
+	}
+
+	/// The rest of the current line, for error messages
+	fn rest_of_line(&self) -> String {
+		self.chars[self.pos..]
+			.iter()
+			.take_while(|c| **c != '\n')
+			.collect::<String>()
+			.trim()
+			.to_string()
+	}
+
+	fn parse(mut self) -> Result<Vec<(String, String)>, EnvironmentError> {
+		let mut vars = vec![];
+		loop {
+			self.skip_blanks();
+			match self.peek() {
+				None => break,
+				Some('\n') | Some('\r') => {
+					self.next();
+				}
+				Some('#') => self.skip_comment(),
+				Some(_) => vars.push(self.parse_assignment()?),
+			}
+		}
+
+		Ok(vars)
+	}
+
+	fn parse_assignment(&mut self) -> Result<(String, String), EnvironmentError> {
+		let line = self.line;
+		let start = self.rest_of_line();
+		let mut key = self.parse_key();
+		if key == "export" && self.peek().is_some_and(|c| c == ' ' || c == '\t') {
+			self.skip_blanks();
+			key = self.parse_key();
+		}
+		if self.next() != Some('=') {
+			return Err(self.error(line, format!("expected KEY=VALUE, not '{}'", start)));
+		}
+		check_key(&key, line)?;
+
+		let value = self.parse_value()?;
+
+		// Only a comment can follow the value
+		self.skip_blanks();
+		match self.peek() {
+			None | Some('\n') | Some('\r') => {}
+			Some('#') => self.skip_comment(),
+			Some(_) => {
+				return Err(self.error(
+					self.line,
+					format!(
+						"unexpected '{}' after the value of {}",
+						self.rest_of_line(),
+						key
+					),
+				))
+			}
+		}
+
+		Ok((key, value))
+	}
+
+	fn parse_key(&mut self) -> String {
+		let mut key = String::new();
+		while let Some(c) = self.peek() {
+			if c == '=' || c.is_whitespace() {
+				break;
+			}
+			key.push(c);
+			self.next();
+		}
+		key
+	}
+
+	fn parse_value(&mut self) -> Result<String, EnvironmentError> {
+		let mut value = String::new();
+		while let Some(c) = self.peek() {
+			match c {
+				' ' | '\t' | '\n' | '\r' => break,
+				'\'' => {
+					let line = self.line;
+					self.next();
+					loop {
+						match self.next() {
+							Some('\'') => break,
+							Some(c) => value.push(c),
+							None => {
+								return Err(self.error(line, String::from("unterminated single quote")))
+							}
+						}
+					}
+				}
+				'"' => {
+					let line = self.line;
+					self.next();
+					loop {
+						match self.next() {
+							Some('"') => break,
+							Some('\\') => match self.next() {
+								// An escaped newline continues the line
+								Some('\n') => {}
+								Some(c @ ('$' | '`' | '"' | '\\')) => value.push(c),
+								Some(c) => {
+									value.push('\\');
+									value.push(c);
+								}
+								None => {
+									return Err(self.error(line, String::from("unterminated double quote")))
+								}
+							},
+							Some(c) => value.push(c),
+							None => {
+								return Err(self.error(line, String::from("unterminated double quote")))
+							}
+						}
+					}
+				}
+				'\\' => {
+					self.next();
+					match self.next() {
+						Some('\n') | None => {}
+						Some(c) => value.push(c),
+					}
+				}
+				c => {
+					value.push(c);
+					self.next();
+				}
+			}
+		}
+
+		Ok(value)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{parse, EnvironmentError};
+
+	fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+		pairs
+			.iter()
+			.map(|(key, value)| (String::from(*key), String::from(*value)))
+			.collect()
+	}
+
+	#[test]
+	fn it_parses_shell_quoting() {
+		let output = r#"
+# comment
+PLAIN=/home/fox/.config
+export EDITOR='nvim -p'
+GREETING="say \"woof\" to \$USER \n"
+ESCAPED=a\ b\'c  # trailing comment
+EMPTY=
+MIXED='it'"'"'s'
+URL=https://example.com/?a=b#c
+MULTI='first
+second'
+"#;
+		assert_eq!(
+			parse(output),
+			Ok(vars(&[
+				("PLAIN", "/home/fox/.config"),
+				("EDITOR", "nvim -p"),
+				("GREETING", r#"say "woof" to $USER \n"#),
+				("ESCAPED", "a b'c"),
+				("EMPTY", ""),
+				("MIXED", "it's"),
+				("URL", "https://example.com/?a=b#c"),
+				("MULTI", "first\nsecond"),
+			]))
+		);
+		assert_eq!(parse(""), Ok(vec![]));
+	}
+
+	#[test]
+	fn it_parses_nul_separated_entries() {
+		assert_eq!(
+			parse("A=1\0B=two\nlines\0C=\"kept\"\0"),
+			Ok(vars(&[("A", "1"), ("B", "two\nlines"), ("C", "\"kept\"")]))
+		);
+		assert_eq!(parse("A=1\0B=2\nx\0nope\0").unwrap_err().line, 2);
+	}
+
+	#[test]
+	fn it_reports_the_line_of_errors() {
+		let error = |output: &str| parse(output).unwrap_err();
+
+		assert_eq!(
+			error("A=1\n\nnot an assignment\n"),
+			EnvironmentError {
+				line: 3,
+				message: String::from("expected KEY=VALUE, not 'not an assignment'")
+			}
+		);
+		assert_eq!(
+			error("A=1\nB='open\n\nC=2"),
+			EnvironmentError {
+				line: 2,
+				message: String::from("unterminated single quote")
+			}
+		);
+		assert_eq!(error("A=\"open").line, 1);
+		assert_eq!(error("A=1\nB=\"open\\").line, 2);
+		assert_eq!(
+			error("A=1\n1A=2"),
+			EnvironmentError {
+				line: 2,
+				message: String::from("invalid variable name '1A'")
+			}
+		);
+		assert_eq!(
+			error("A='x\ny' z"),
+			EnvironmentError {
+				line: 2,
+				message: String::from("unexpected 'z' after the value of A")
+			}
+		);
+	}
+}
@@ -0,0 +1,220 @@
+//! The error type returned throughout the crate
+
+use std::{
+	fmt, io,
+	path::{Path, PathBuf},
+	process::ExitStatus,
+	time::Duration,
+};
+
+use crate::metadata::format_duration;
+
+/// A result with the crate error
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// An error, with the path, command, or dotfile it happened with
+#[derive(Debug)]
+pub enum Error {
+	/// A configuration file or value is invalid
+	Config { origin: String, message: String },
+	/// A filesystem operation failed
+	Io { path: PathBuf, source: io::Error },
+	/// The terminal could not be set up or drawn to
+	Terminal(io::Error),
+	/// A command could not be started
+	Spawn { command: PathBuf, source: io::Error },
+	/// A command exited unsuccessfully
+	CommandFailed {
+		command: PathBuf,
+		status: ExitStatus,
+		stderr: String,
+	},
+	/// A script could not be selected, created, or run, like when no script
+	/// or more than one matched, or scripts require each other
+	Script { message: String },
+	/// A dotfile entry could not be reconciled
+	Entry { target: PathBuf, source: Box<Error> },
+	/// The dotfiles directory could not be watched
+	Watch { message: String },
+	/// The daemon could not be reached, or it returned an error
+	Daemon { message: String },
+	/// The doctor found problems
+	Doctor { problems: usize },
+	/// The feature is not supported here
+	Unsupported { message: String },
+	/// The metadata header of a script is invalid
+	Metadata {
+		path: PathBuf,
+		line: usize,
+		message: String,
+	},
+	/// Scripts failed when running several
+	ScriptsFailed { failed: usize },
+	/// The history of script runs is invalid
+	History { path: PathBuf, message: String },
+	/// A script exited unsuccessfully. The process exits with the same code,
+	/// which may be one of the codes of other errors
+	ScriptExit { script: String, status: ExitStatus },
+	/// Checking scripts found errors
+	Lint { errors: usize },
+	/// A script ran longer than its timeout, and was stopped
+	ScriptTimeout { script: String, timeout: Duration },
+	/// A script was stopped by Ctrl-C, or `dotfile` was asked to stop
+	Interrupted { script: String },
+	/// The output of `environment.sh` is invalid
+	Environment {
+		path: PathBuf,
+		line: usize,
+		message: String,
+	},
+}
+
+impl Error {
+	/// An error from a filesystem operation on `path`
+	pub fn io(path: &Path, source: io::Error) -> Error {
+		Error::Io {
+			path: path.to_path_buf(),
+			source,
+		}
+	}
+
+	/// An error from starting `command`
+	pub fn spawn(command: &Path, source: io::Error) -> Error {
+		Error::Spawn {
+			command: command.to_path_buf(),
+			source,
+		}
+	}
+
+	/// An error selecting, creating, or running a script
+	pub fn script(message: impl Into<String>) -> Error {
+		Error::Script {
+			message: message.into(),
+		}
+	}
+
+	/// The exit code of the process when this error reaches `main`. Codes
+	/// start at 3, since clap exits with 2 on usage errors. Stopped scripts
+	/// exit like `timeout` and shells do
+	pub fn exit_code(&self) -> i32 {
+		match self {
+			Error::Config { .. } => 3,
+			Error::Io { .. } => 4,
+			Error::Terminal(_) => 4,
+			Error::Spawn { .. } => 5,
+			Error::CommandFailed { .. } => 6,
+			Error::Script { .. } => 7,
+			Error::Entry { source, .. } => source.exit_code(),
+			Error::Watch { .. } => 8,
+			Error::Daemon { .. } => 9,
+			Error::Doctor { .. } => 10,
+			Error::Unsupported { .. } => 11,
+			Error::Metadata { .. } => 12,
+			Error::ScriptsFailed { .. } => 13,
+			Error::History { .. } => 14,
+			Error::ScriptExit { status, .. } => status_code(status),
+			Error::Lint { .. } => 15,
+			Error::Environment { .. } => 16,
+			Error::ScriptTimeout { .. } => 124,
+			Error::Interrupted { .. } => 130,
+		}
+	}
+}
+
+/// The exit code a shell gives for a status: the code of the process, or 128
+/// plus the signal that killed it
+fn status_code(status: &ExitStatus) -> i32 {
+	#[cfg(unix)]
+	if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(status) {
+		return 128 + signal;
+	}
+
+	status.code().unwrap_or(1)
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Error::Config { origin, message } => write!(f, "{}: {}", origin, message),
+			Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+			Error::Terminal(source) => write!(f, "terminal: {}", source),
+			Error::Spawn { command, source } => {
+				write!(f, "Failed to run {}: {}", command.display(), source)
+			}
+			Error::CommandFailed {
+				command,
+				status,
+				stderr,
+			} => {
+				write!(f, "{} failed with {}", command.display(), status)?;
+				if !stderr.trim().is_empty() {
+					write!(f, "\n{}", stderr.trim_end())?;
+				}
+				Ok(())
+			}
+			Error::Script { message } => write!(f, "{}", message),
+			Error::Entry { target, source } => write!(f, "{}: {}", target.display(), source),
+			Error::Watch { message } => write!(f, "Failed to watch: {}", message),
+			Error::Daemon { message } => write!(f, "{}", message),
+			Error::Doctor { problems } => write!(f, "{} problem(s) found", problems),
+			Error::Unsupported { message } => write!(f, "{}", message),
+			Error::Metadata {
+				path,
+				line,
+				message,
+			} => write!(f, "{}:{}: {}", path.display(), line, message),
+			Error::ScriptsFailed { failed } => write!(f, "{} script(s) failed", failed),
+			Error::History { path, message } => write!(f, "{}: {}", path.display(), message),
+			Error::ScriptExit { script, status } => write!(f, "{} failed with {}", script, status),
+			Error::Lint { errors } => write!(f, "{} error(s) found in scripts", errors),
+			Error::ScriptTimeout { script, timeout } => write!(
+				f,
+				"{} timed out after {}, and was stopped",
+				script,
+				format_duration(*timeout)
+			),
+			Error::Interrupted { script } => write!(f, "{} was interrupted", script),
+			Error::Environment {
+				path,
+				line,
+				message,
+			} => write!(
+				f,
+				"{}: line {} of the output: {}",
+				path.display(),
+				line,
+				message
+			),
+		}
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Io { source, .. } => Some(source),
+			Error::Terminal(source) => Some(source),
+			Error::Spawn { source, .. } => Some(source),
+			Error::Entry { source, .. } => Some(source.as_ref()),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+	use std::{ffi::OsStr, io, os::unix::ffi::OsStrExt, path::Path};
+
+	use super::Error;
+	use crate::config::Origin;
+
+	#[test]
+	fn it_displays_paths_that_are_not_utf8() {
+		let path = Path::new(OsStr::from_bytes(b"/home/fox/\xff.toml"));
+		let err = Error::io(path, io::Error::from(io::ErrorKind::NotFound));
+		assert!(err.to_string().starts_with("/home/fox/\u{fffd}.toml: "));
+
+		let origin = Origin::File(path.to_path_buf());
+		assert_eq!(origin.to_string(), "file /home/fox/\u{fffd}.toml");
+	}
+}
@@ -0,0 +1,95 @@
+//! Fuzzy matching, to select scripts by part of their name
+
+/// Whether a character separates the words of a name, like `-` in `git-setup`
+fn is_separator(c: char) -> bool {
+	matches!(c, '-' | '_' | '.' | ' ' | '/')
+}
+
+/// Score how well `query` matches `candidate`, ignoring case. Every character
+/// of the query must appear in the candidate, in order. Characters that follow
+/// each other, or start a word, score higher. Returns `None` if the query does
+/// not match
+pub fn score(query: &str, candidate: &str) -> Option<i32> {
+	let query: Vec<char> = query.to_lowercase().chars().collect();
+	let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+	let first = *query.first()?;
+
+	// Try each place the query can start, since the first is not always best
+	(0..candidate.len())
+		.filter(|&start| candidate[start] == first)
+		.filter_map(|start| score_from(&query, &candidate, start))
+		.max()
+}
+
+fn score_from(query: &[char], candidate: &[char], start: usize) -> Option<i32> {
+	let mut score = 0;
+	let mut prev: Option<usize> = None;
+	let mut i = start;
+	for &c in query {
+		while candidate.get(i)? != &c {
+			i += 1;
+		}
+
+		score += 1;
+		if prev.is_some_and(|prev| prev + 1 == i) {
+			score += 5;
+		} else if prev.is_some() {
+			// Skipped characters cost a little
+			score -= 1;
+		}
+		if i == 0 || is_separator(candidate[i - 1]) {
+			score += 3;
+		}
+
+		prev = Some(i);
+		i += 1;
+	}
+
+	Some(score)
+}
+
+/// The number of characters to insert, delete, or substitute to turn `a` into
+/// `b`, ignoring case
+pub fn distance(a: &str, b: &str) -> usize {
+	let a: Vec<char> = a.to_lowercase().chars().collect();
+	let b: Vec<char> = b.to_lowercase().chars().collect();
+
+	let mut prev: Vec<usize> = (0..=b.len()).collect();
+	for i in 1..=a.len() {
+		let mut row = vec![i; b.len() + 1];
+		for j in 1..=b.len() {
+			let cost = usize::from(a[i - 1] != b[j - 1]);
+			row[j] = (prev[j] + 1).min(row[j - 1] + 1).min(prev[j - 1] + cost);
+		}
+		prev = row;
+	}
+
+	prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{distance, score};
+
+	#[test]
+	fn it_scores_matches() {
+		assert_eq!(score("xyz", "nvim.sh"), None);
+		assert_eq!(score("", "nvim.sh"), None);
+		assert!(score("NV", "nvim.sh").is_some());
+
+		// Consecutive characters beat scattered ones
+		assert!(score("git", "git-setup.sh") > score("git", "go-install-tools.sh"));
+		// Word starts beat the middle of words
+		assert!(score("rust", "install-rust.sh") > score("rust", "trusty.sh"));
+		// A later start can score higher than the first one
+		assert!(score("set", "s-setup.sh") > score("set", "sxexxt.sh"));
+	}
+
+	#[test]
+	fn it_measures_distance() {
+		assert_eq!(distance("nvim", "nvim"), 0);
+		assert_eq!(distance("nvmi", "nvim"), 2);
+		assert_eq!(distance("Vim", "nvim"), 1);
+		assert_eq!(distance("", "abc"), 3);
+	}
+}
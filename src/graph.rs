@@ -0,0 +1,369 @@
+//! The graph of scripts that require other scripts to run first
+
+use std::{cell::OnceCell, fmt::Write};
+
+use crate::{
+	error::{Error, Result},
+	metadata::ScriptInfo,
+	util::Script,
+};
+
+/// The scripts of the categories, and the scripts each one requires. The metadata
+/// of a script is only read when it is needed, so that invalid metadata only
+/// fails for the scripts that reach it
+pub struct ScriptGraph {
+	scripts: Vec<Script>,
+	// The metadata of each script, once read
+	infos: Vec<OnceCell<ScriptInfo>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Visit {
+	New,
+	InProgress,
+	Done,
+}
+
+/// Whether `name` refers to `script`, by its id, file name, or file name
+/// without the extension. The name can start with the repository or category
+/// of the script, like `repo/category/name`
+fn is_named(script: &Script, name: &str) -> bool {
+	let mut name = name;
+	while let Some((prefix, rest)) = name.split_once('/') {
+		if prefix != script.repo.name && prefix != script.category {
+			return false;
+		}
+		name = rest;
+	}
+
+	let path = &script.path;
+	path.file_name().is_some_and(|val| val == name)
+		|| path.file_stem().is_some_and(|val| val == name)
+}
+
+impl ScriptGraph {
+	/// Build the graph of the runnable scripts
+	pub fn new(scripts: Vec<Script>) -> ScriptGraph {
+		let scripts: Vec<Script> = scripts
+			.into_iter()
+			.filter(|script| script.is_runnable())
+			.collect();
+
+		ScriptGraph {
+			infos: scripts.iter().map(|_| OnceCell::new()).collect(),
+			scripts,
+		}
+	}
+
+	/// Get every script in the graph
+	pub fn scripts(&self) -> &[Script] {
+		&self.scripts
+	}
+
+	fn index_of(&self, script: &Script) -> Result<usize> {
+		self
+			.scripts
+			.iter()
+			.position(|val| val.file() == script.file())
+			.ok_or_else(|| Error::script(format!("{} cannot be run", script.id)))
+	}
+
+	fn info_at(&self, i: usize) -> Result<&ScriptInfo> {
+		if let Some(info) = self.infos[i].get() {
+			return Ok(info);
+		}
+		let info = self.scripts[i].info()?;
+		Ok(self.infos[i].get_or_init(|| info))
+	}
+
+	/// Get the metadata of `script`. Fails if it is invalid
+	pub fn info(&self, script: &Script) -> Result<&ScriptInfo> {
+		self.info_at(self.index_of(script)?)
+	}
+
+	/// Get the indices of the scripts that the script at `i` requires. Fails
+	/// if its metadata is invalid, or it requires a script that does not exist
+	fn edges(&self, i: usize) -> Result<Vec<usize>> {
+		let script = &self.scripts[i];
+		let mut requires = vec![];
+		for name in &self.info_at(i)?.requires {
+			// Prefer a script from the same category, then from the same
+			// repository
+			let index = (0..self.scripts.len())
+				.filter(|&j| is_named(&self.scripts[j], name))
+				.min_by_key(|&j| {
+					let other = &self.scripts[j];
+					(
+						other.category != script.category,
+						other.repo.name != script.repo.name,
+					)
+				});
+
+			match index {
+				Some(j) => requires.push(j),
+				None => {
+					return Err(Error::script(format!(
+						"{} requires '{}', which does not exist",
+						script.id, name
+					)))
+				}
+			}
+		}
+
+		Ok(requires)
+	}
+
+	/// Get the edges of every script
+	fn all_edges(&self) -> Result<Vec<Vec<usize>>> {
+		(0..self.scripts.len()).map(|i| self.edges(i)).collect()
+	}
+
+	/// Get the scripts that `script` requires directly
+	pub fn requirements(&self, script: &Script) -> Result<Vec<&Script>> {
+		let i = self.index_of(script)?;
+		Ok(self
+			.edges(i)?
+			.into_iter()
+			.map(|j| &self.scripts[j])
+			.collect())
+	}
+
+	fn visit(
+		&self,
+		i: usize,
+		visits: &mut [Visit],
+		stack: &mut Vec<usize>,
+		order: &mut Vec<usize>,
+	) -> Result<()> {
+		match visits[i] {
+			Visit::Done => return Ok(()),
+			Visit::InProgress => {
+				let start = stack.iter().position(|&j| j == i).unwrap();
+				let cycle: Vec<&str> = stack[start..]
+					.iter()
+					.chain([&i])
+					.map(|&j| self.scripts[j].id.as_str())
+					.collect();
+				return Err(Error::script(format!(
+					"Scripts require each other: {}",
+					cycle.join(" -> ")
+				)));
+			}
+			Visit::New => {}
+		}
+
+		visits[i] = Visit::InProgress;
+		stack.push(i);
+		for j in self.edges(i)? {
+			self.visit(j, visits, stack, order)?;
+		}
+		stack.pop();
+		visits[i] = Visit::Done;
+		order.push(i);
+
+		Ok(())
+	}
+
+	/// Get the scripts to run for each of `targets`, in order: every script
+	/// they require, directly or not, then the targets. Each script appears
+	/// once. Fails if scripts require each other, or a script reached has
+	/// invalid metadata
+	pub fn run_order(&self, targets: &[&Script]) -> Result<Vec<&Script>> {
+		let mut visits = vec![Visit::New; self.scripts.len()];
+		let mut order = vec![];
+		for target in targets {
+			let i = self.index_of(target)?;
+			self.visit(i, &mut visits, &mut vec![], &mut order)?;
+		}
+
+		Ok(order.into_iter().map(|i| &self.scripts[i]).collect())
+	}
+
+	/// Get `targets` and every script they require, directly or not, in the
+	/// order of the graph. Unlike `run_order`, scripts may require each other
+	pub fn with_requirements(&self, targets: &[&Script]) -> Result<Vec<&Script>> {
+		let mut reached = vec![false; self.scripts.len()];
+		let mut stack = targets
+			.iter()
+			.map(|target| self.index_of(target))
+			.collect::<Result<Vec<usize>>>()?;
+		while let Some(i) = stack.pop() {
+			if !reached[i] {
+				reached[i] = true;
+				stack.extend(self.edges(i)?);
+			}
+		}
+
+		Ok(self
+			.scripts
+			.iter()
+			.zip(reached)
+			.filter_map(|(script, reached)| reached.then_some(script))
+			.collect())
+	}
+
+	/// Check that no scripts require each other
+	pub fn check_cycles(&self) -> Result<()> {
+		let targets: Vec<&Script> = self.scripts.iter().collect();
+		self.run_order(&targets).map(|_| ())
+	}
+
+	/// Render the graph as a tree for each script that no other script
+	/// requires
+	pub fn to_text(&self) -> Result<String> {
+		self.check_cycles()?;
+		let edges = self.all_edges()?;
+
+		let mut text = String::new();
+		for i in 0..self.scripts.len() {
+			if !edges.iter().any(|requires| requires.contains(&i)) {
+				self.write_tree(&edges, &mut text, i, "", "");
+			}
+		}
+
+		Ok(text)
+	}
+
+	fn write_tree(
+		&self,
+		edges: &[Vec<usize>],
+		text: &mut String,
+		i: usize,
+		prefix: &str,
+		child_prefix: &str,
+	) {
+		writeln!(text, "{}{}", prefix, self.scripts[i].id).unwrap();
+
+		let requires = &edges[i];
+		for (n, &j) in requires.iter().enumerate() {
+			match n + 1 == requires.len() {
+				true => self.write_tree(
+					edges,
+					text,
+					j,
+					&format!("{}└── ", child_prefix),
+					&format!("{}    ", child_prefix),
+				),
+				false => self.write_tree(
+					edges,
+					text,
+					j,
+					&format!("{}├── ", child_prefix),
+					&format!("{}│   ", child_prefix),
+				),
+			}
+		}
+	}
+
+	/// Render the graph in the DOT language of Graphviz, with an edge from
+	/// each script to the scripts it requires
+	pub fn to_dot(&self) -> Result<String> {
+		let edges = self.all_edges()?;
+
+		let mut dot = String::from("digraph scripts {\n");
+		for (i, script) in self.scripts.iter().enumerate() {
+			writeln!(dot, "\t{:?};", script.id).unwrap();
+			for &j in &edges[i] {
+				writeln!(dot, "\t{:?} -> {:?};", script.id, self.scripts[j].id).unwrap();
+			}
+		}
+		dot.push_str("}\n");
+
+		Ok(dot)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{fs, path::Path};
+
+	use super::ScriptGraph;
+	use crate::{
+		test_util::{self, TempDir},
+		util::Script,
+	};
+
+	fn script(dir: &Path, name: &str, content: &str) -> Script {
+		let path = dir.join(name);
+		fs::write(&path, content).unwrap();
+		Script {
+			repo: test_util::repo(dir),
+			category: String::from("default"),
+			id: String::from(name),
+			path,
+		}
+	}
+
+	#[test]
+	fn it_orders_required_scripts_first() {
+		let dir = TempDir::new("graph");
+		fs::create_dir_all(dir.join("impl")).unwrap();
+		fs::write(dir.join("impl/entrypoint.sh"), "").unwrap();
+
+		let scripts = vec![
+			script(&dir, "a.sh", "# requires: b, c.sh"),
+			script(&dir, "b.sh", "# requires: c"),
+			script(&dir, "c.sh", ""),
+		];
+		let graph = ScriptGraph::new(scripts.clone());
+		let ids = |order: Vec<&Script>| order.iter().map(|s| s.id.clone()).collect::<Vec<_>>();
+		assert_eq!(
+			ids(graph.run_order(&[&scripts[0]]).unwrap()),
+			["c.sh", "b.sh", "a.sh"]
+		);
+		assert_eq!(
+			ids(graph.run_order(&[&scripts[1], &scripts[0]]).unwrap()),
+			["c.sh", "b.sh", "a.sh"]
+		);
+		assert_eq!(
+			graph.to_text().unwrap(),
+			"a.sh\n├── b.sh\n│   └── c.sh\n└── c.sh\n"
+		);
+
+		let scripts = vec![
+			script(&dir, "a.sh", "# requires: b"),
+			script(&dir, "b.sh", "# requires: a"),
+			script(&dir, "c.sh", "# requires: a"),
+		];
+		let graph = ScriptGraph::new(scripts.clone());
+		assert_eq!(
+			graph.run_order(&[&scripts[2]]).err().unwrap().to_string(),
+			"Scripts require each other: a.sh -> b.sh -> a.sh"
+		);
+
+		assert_eq!(
+			ids(graph.with_requirements(&[&scripts[2]]).unwrap()),
+			["a.sh", "b.sh", "c.sh"]
+		);
+		assert_eq!(
+			ids(graph.with_requirements(&[&scripts[0]]).unwrap()),
+			["a.sh", "b.sh"]
+		);
+
+		let scripts = vec![script(&dir, "a.sh", "# requires: d")];
+		assert_eq!(
+			ScriptGraph::new(scripts.clone())
+				.run_order(&[&scripts[0]])
+				.err()
+				.unwrap()
+				.to_string(),
+			"a.sh requires 'd', which does not exist"
+		);
+
+		// Invalid metadata only fails for the scripts that reach it
+		let scripts = vec![
+			script(&dir, "a.sh", "# requires: b"),
+			script(&dir, "b.sh", ""),
+			script(&dir, "broken.sh", "# order: first"),
+			script(&dir, "c.sh", "# requires: broken"),
+		];
+		let graph = ScriptGraph::new(scripts.clone());
+		assert_eq!(
+			ids(graph.run_order(&[&scripts[0]]).unwrap()),
+			["b.sh", "a.sh"]
+		);
+		assert!(graph.info(&scripts[2]).is_err());
+		assert!(graph.run_order(&[&scripts[3]]).is_err());
+		assert!(graph.to_text().is_err());
+	}
+}
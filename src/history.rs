@@ -0,0 +1,194 @@
+//! The history of script runs
+//!
+//! Each run of a script is appended to a TOML file in the state directory, so
+//! that scripts which already ran successfully can be skipped:
+//!
+//! ```toml
+//! [[run]]
+//! id = "main/nvim.sh"
+//! path = "/home/fox/.dotfiles/os/unix/dotmgr/scripts/nvim.sh"
+//! hash = "af63bd4c8601b7be"
+//! host = "laptop"
+//! start = 1700000000
+//! end = 1700000012
+//! success = true
+//! exit_code = 0
+//! ```
+//!
+//! With `--log`, the output of a run is also kept in a log file under `logs/`
+
+use std::{
+	fs::{self, OpenOptions},
+	io::{self, Write},
+	path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	error::{Error, Result},
+	util::{self, Script},
+};
+
+/// One run of a script
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScriptRun {
+	/// The name the script was shown as
+	pub id: String,
+	/// The path to the file that ran
+	pub path: PathBuf,
+	/// The hash of the content of the file when it ran, from `content_hash`
+	pub hash: String,
+	/// The machine it ran on
+	pub host: String,
+	/// When the script started, in seconds since the Unix epoch
+	pub start: u64,
+	/// When the script ended, in seconds since the Unix epoch
+	pub end: u64,
+	/// Whether the script exited successfully
+	pub success: bool,
+	/// The exit code, if the script was not killed by a signal
+	pub exit_code: Option<i32>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct HistoryFile {
+	#[serde(default)]
+	run: Vec<ScriptRun>,
+}
+
+/// Get the path of the history file in the state directory
+pub fn get_history_path() -> PathBuf {
+	util::get_state_dir().join("history.toml")
+}
+
+/// Get the path of a new log file for a run of `script` that starts at
+/// `start`, in milliseconds since the Unix epoch
+pub fn get_log_path(script: &Script, start: u128) -> PathBuf {
+	get_log_dir(script).join(format!("{:016}.log", start))
+}
+
+/// Find the log of the latest run of `script` that kept one
+pub fn find_latest_log(script: &Script) -> Option<PathBuf> {
+	fs::read_dir(get_log_dir(script))
+		.ok()?
+		.filter_map(|entry| entry.ok())
+		.map(|entry| entry.path())
+		.filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+		.max()
+}
+
+fn get_log_dir(script: &Script) -> PathBuf {
+	let file = script.file();
+	let name = file.strip_prefix(&script.repo.dotmgr_dir).unwrap_or(&file);
+	util::get_state_dir()
+		.join("logs")
+		.join(&script.repo.name)
+		.join(name)
+}
+
+/// Hash the content of a script, with 64-bit FNV-1a. The hash is stable across
+/// versions, unlike the hasher of the standard library
+pub fn content_hash(content: &[u8]) -> String {
+	let mut hash: u64 = 0xcbf29ce484222325;
+	for byte in content {
+		hash ^= u64::from(*byte);
+		hash = hash.wrapping_mul(0x100000001b3);
+	}
+	format!("{:016x}", hash)
+}
+
+/// Read every run from the history file at `path`, oldest first. The history
+/// is empty if the file does not exist
+pub fn read_history(path: &Path) -> Result<Vec<ScriptRun>> {
+	let content = match fs::read_to_string(path) {
+		Ok(val) => val,
+		Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+		Err(err) => return Err(Error::io(path, err)),
+	};
+	let file: HistoryFile = toml::from_str(&content).map_err(|err| Error::History {
+		path: path.to_path_buf(),
+		message: err.to_string(),
+	})?;
+
+	Ok(file.run)
+}
+
+/// Append a run to the history file at `path`, creating it if needed
+pub fn append_history(path: &Path, run: &ScriptRun) -> Result<()> {
+	if let Some(dir) = path.parent() {
+		fs::create_dir_all(dir).map_err(|err| Error::io(dir, err))?;
+	}
+	let content = toml::to_string(&HistoryFile {
+		run: vec![run.clone()],
+	})
+	.map_err(|err| Error::History {
+		path: path.to_path_buf(),
+		message: err.to_string(),
+	})?;
+
+	let mut file = OpenOptions::new()
+		.create(true)
+		.append(true)
+		.open(path)
+		.map_err(|err| Error::io(path, err))?;
+	writeln!(file, "{}", content).map_err(|err| Error::io(path, err))
+}
+
+/// Whether the script at `path` already ran successfully on `host`, with the
+/// content it has now
+pub fn has_succeeded(history: &[ScriptRun], path: &Path, hash: &str, host: &str) -> bool {
+	history
+		.iter()
+		.any(|run| run.success && run.path == path && run.hash == hash && run.host == host)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::path::PathBuf;
+
+	use super::{append_history, content_hash, has_succeeded, read_history, ScriptRun};
+	use crate::test_util::TempDir;
+
+	#[test]
+	fn it_appends_and_reads_runs() {
+		let dir = TempDir::new("history");
+		let path = dir.join("history.toml");
+		assert_eq!(read_history(&path).unwrap(), vec![]);
+
+		let run = ScriptRun {
+			id: String::from("main/nvim.sh"),
+			path: PathBuf::from("/dots/nvim.sh"),
+			hash: content_hash(b"echo woof"),
+			host: String::from("laptop"),
+			start: 1,
+			end: 2,
+			success: false,
+			exit_code: Some(3),
+		};
+		append_history(&path, &run).unwrap();
+		let killed = ScriptRun {
+			success: true,
+			exit_code: None,
+			..run.clone()
+		};
+		append_history(&path, &killed).unwrap();
+		let history = read_history(&path).unwrap();
+		assert_eq!(history, vec![run.clone(), killed]);
+
+		assert!(has_succeeded(&history, &run.path, &run.hash, "laptop"));
+		assert!(!has_succeeded(&history, &run.path, &run.hash, "desktop"));
+		assert!(!has_succeeded(
+			&history,
+			&run.path,
+			&content_hash(b"echo bark"),
+			"laptop"
+		));
+	}
+
+	#[test]
+	fn it_hashes_content_stably() {
+		assert_eq!(content_hash(b""), "cbf29ce484222325");
+		assert_eq!(content_hash(b"a"), "af63dc4c8601ec8c");
+	}
+}
@@ -1 +1,38 @@
-pub struct F;
+//! The library behind the `dotfile` command line tool. It reads the
+//! configuration, lists the dotfiles that `deploy.sh` prints, reconciles them
+//! with the home directory, and finds the scripts of each dotmgr directory.
+//!
+//! ```no_run
+//! use std::io;
+//!
+//! use fox_dotfile::{
+//!     config::{Config, ConfigOverrides},
+//!     reconcile::{get_dotfile_list, reconcile_dotfiles, ReconcileAction},
+//! };
+//!
+//! let config = Config::load(ConfigOverrides::default())?;
+//! let dotfiles = get_dotfile_list(&config)?;
+//! reconcile_dotfiles(
+//!     &dotfiles,
+//!     ReconcileAction::Deploy,
+//!     config.conflict_policy,
+//!     &mut io::stdout(),
+//! )?;
+//! # Ok::<(), fox_dotfile::error::Error>(())
+//! ```
+
+pub mod category;
+pub mod config;
+pub mod environment;
+pub mod error;
+pub mod fuzzy;
+pub mod graph;
+pub mod history;
+pub mod lint;
+pub mod metadata;
+pub mod process;
+pub mod reconcile;
+pub mod util;
+
+#[cfg(test)]
+mod test_util;
@@ -0,0 +1,213 @@
+//! Static checks of scripts with shellcheck
+//!
+//! The utility scripts are sourced at the top of the script before it is
+//! checked, like the entrypoint does, so that functions and variables they
+//! define are known. Findings are reported at the lines of the script itself
+
+use std::{
+	fmt, fs,
+	io::Write,
+	path::{Path, PathBuf},
+	process::{Command, Stdio},
+};
+
+use crate::{
+	error::{Error, Result},
+	util,
+};
+
+/// How serious a finding is
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+	/// Style and information
+	Note,
+	Warning,
+	Error,
+}
+
+impl fmt::Display for Severity {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Severity::Note => write!(f, "note"),
+			Severity::Warning => write!(f, "warning"),
+			Severity::Error => write!(f, "error"),
+		}
+	}
+}
+
+/// A problem found in a script
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+	/// The line, starting at 1
+	pub line: usize,
+	/// The column, starting at 1
+	pub column: usize,
+	pub severity: Severity,
+	pub message: String,
+}
+
+/// Whether `shellcheck` is installed
+pub fn has_shellcheck() -> bool {
+	util::find_command("shellcheck").is_some()
+}
+
+/// Whether the script at `path` is a shell script shellcheck can check: it
+/// ends with `.sh`, or has a shebang for sh, bash, dash, or ksh
+pub fn is_shell_script(path: &Path) -> bool {
+	if path.extension().is_some_and(|ext| ext == "sh") {
+		return true;
+	}
+
+	let Ok(content) = fs::read(path) else {
+		return false;
+	};
+	let content = String::from_utf8_lossy(&content);
+	let Some(shebang) = content
+		.lines()
+		.next()
+		.and_then(|line| line.strip_prefix("#!"))
+	else {
+		return false;
+	};
+	shebang
+		.split_whitespace()
+		.filter_map(|word| Path::new(word).file_name()?.to_str())
+		.any(|word| matches!(word, "sh" | "bash" | "dash" | "ksh"))
+}
+
+/// Check the shell script at `path` with shellcheck, after sourcing each of
+/// `sources`
+pub fn shellcheck(path: &Path, sources: &[PathBuf]) -> Result<Vec<Finding>> {
+	let content = fs::read(path).map_err(|err| Error::io(path, err))?;
+	let content = String::from_utf8_lossy(&content);
+
+	// The sources go on one line after the shebang, so lines after it are off
+	// by one
+	let source_line: Vec<String> = sources
+		.iter()
+		.map(|source| {
+			let source = source.to_string_lossy();
+			format!(". '{}';", source.replace('\'', r"'\''"))
+		})
+		.collect();
+	let source_line = format!("{} :", source_line.join(" "));
+	let has_shebang = content.starts_with("#!");
+	let (inserted_at, input) = match content.split_once('\n') {
+		Some((first, rest)) if has_shebang => (2, format!("{}\n{}\n{}", first, source_line, rest)),
+		_ if has_shebang => (2, format!("{}\n{}\n", content, source_line)),
+		_ => (1, format!("{}\n{}", source_line, content)),
+	};
+
+	let mut command = Command::new("shellcheck");
+	command.args(["--format=gcc", "--external-sources"]);
+	if !has_shebang {
+		// The entrypoint runs scripts without a shebang with bash
+		command.arg("--shell=bash");
+	}
+	let mut child = command
+		.arg("-")
+		.current_dir(path.parent().unwrap())
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped())
+		.spawn()
+		.map_err(|err| Error::spawn(Path::new("shellcheck"), err))?;
+	child
+		.stdin
+		.take()
+		.unwrap()
+		.write_all(input.as_bytes())
+		.map_err(|err| Error::spawn(Path::new("shellcheck"), err))?;
+	let output = child
+		.wait_with_output()
+		.map_err(|err| Error::spawn(Path::new("shellcheck"), err))?;
+
+	// shellcheck exits with 1 when it finds problems, and with more on failure
+	if output.status.code().is_none_or(|code| code > 1) {
+		return Err(Error::CommandFailed {
+			command: Path::new("shellcheck").to_path_buf(),
+			status: output.status,
+			stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+		});
+	}
+
+	Ok(parse_shellcheck_output(
+		&String::from_utf8_lossy(&output.stdout),
+		inserted_at,
+	))
+}
+
+/// Parse the `gcc` format of shellcheck for standard input, moving findings
+/// after the inserted line `inserted_at` up by one, and dropping findings on it
+fn parse_shellcheck_output(output: &str, inserted_at: usize) -> Vec<Finding> {
+	let mut findings = vec![];
+	for line in output.lines() {
+		// Findings in sourced files are for the utility scripts, not this one
+		let Some(rest) = line.strip_prefix("-:") else {
+			continue;
+		};
+		let mut parts = rest.splitn(3, ':');
+		let (Some(line), Some(column), Some(rest)) = (parts.next(), parts.next(), parts.next())
+		else {
+			continue;
+		};
+		let (Ok(line), Ok(column)) = (line.parse::<usize>(), column.parse::<usize>()) else {
+			continue;
+		};
+		let Some((severity, message)) = rest.trim().split_once(": ") else {
+			continue;
+		};
+
+		let line = match line {
+			line if line == inserted_at => continue,
+			line if line > inserted_at => line - 1,
+			line => line,
+		};
+		findings.push(Finding {
+			line,
+			column,
+			severity: match severity {
+				"error" => Severity::Error,
+				"warning" => Severity::Warning,
+				_ => Severity::Note,
+			},
+			message: String::from(message),
+		});
+	}
+
+	findings
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{parse_shellcheck_output, Finding, Severity};
+
+	#[test]
+	fn it_parses_shellcheck_output() {
+		let output = "\
+-:1:1: error: Tips depend on target shell and yours is unknown. [SC2148]
+-:2:3: note: Not following: ./util/log.sh was not specified as input [SC1091]
+-:5:7: warning: foo appears unused. Verify use (or export if used externally). [SC2034]
+/dots/util/log.sh:3:1: warning: bar appears unused. [SC2034]
+";
+		assert_eq!(
+			parse_shellcheck_output(output, 2),
+			vec![
+				Finding {
+					line: 1,
+					column: 1,
+					severity: Severity::Error,
+					message: String::from("Tips depend on target shell and yours is unknown. [SC2148]"),
+				},
+				Finding {
+					line: 4,
+					column: 7,
+					severity: Severity::Warning,
+					message: String::from(
+						"foo appears unused. Verify use (or export if used externally). [SC2034]"
+					),
+				},
+			]
+		);
+	}
+}
@@ -1,24 +1,54 @@
+use std::process;
+
 use clap::Parser;
+use colored::Colorize;
 
 mod cli;
 mod commands;
-mod config;
 mod tui;
-mod util;
 
-use crate::cli::{Cli, CliCommands, InternalCommands, ReconcileCommands, ScriptCommands};
-use crate::commands::{CommandInternal, CommandReconcile, CommandScript};
-use crate::config::Config;
+use crate::cli::{
+	Cli, CliCommands, ConfigCommands, DaemonCommands, InternalCommands, ReconcileCommands,
+	RepoCommands, ScriptCommands,
+};
+use crate::commands::{
+	CommandConfig, CommandDaemon, CommandDoctor, CommandInit, CommandInternal, CommandReconcile,
+	CommandRepo, CommandScript,
+};
+use fox_dotfile::config::{Config, ConfigOverrides};
+use fox_dotfile::error::Result;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() {
+	if let Err(err) = run() {
+		eprintln!("{} {}", "error:".red(), err);
+		process::exit(err.exit_code());
+	}
+}
+
+fn run() -> Result<()> {
 	let cli = Cli::parse();
-	let config = Config::default();
+	let config = Config::load(ConfigOverrides {
+		dotfiles_dir: cli.dotfiles_dir.clone(),
+		os_dir: cli.os_dir.clone(),
+		dotmgr_dir: cli.dotmgr_dir.clone(),
+		conflict_policy: cli.conflict_policy,
+		editor: cli.editor.clone(),
+		pager: cli.pager.clone(),
+	})?;
 
 	env_logger::Builder::new()
 		.filter_level(cli.verbose.log_level_filter())
 		.init();
 
 	match &cli.command {
+		CliCommands::Init { dir } => {
+			let command_init = CommandInit::new(config);
+			command_init.init(dir.clone())?;
+		}
+		CliCommands::Doctor {} => {
+			let command_doctor = CommandDoctor::new(config);
+			command_doctor.doctor()?;
+		}
 		CliCommands::Script { command, category } => {
 			let command_script = CommandScript::new(config, category.clone());
 
@@ -26,14 +56,47 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 				ScriptCommands::List {} => {
 					command_script.list();
 				}
-				ScriptCommands::View { glob } => {
-					command_script.view(glob.clone());
+				ScriptCommands::Categories {} => {
+					command_script.categories();
+				}
+				ScriptCommands::View { name } => {
+					command_script.view(name.clone())?;
+				}
+				ScriptCommands::Edit { name } => {
+					command_script.edit(name.clone())?;
+				}
+				ScriptCommands::Run {
+					name,
+					sudo,
+					once,
+					log,
+					timeout,
+					args,
+				} => {
+					command_script.run(name.clone(), args, *sudo, *once, *log, *timeout)?;
+				}
+				ScriptCommands::RunAll {
+					keep_going,
+					once,
+					log,
+					timeout,
+				} => {
+					command_script.run_all(*keep_going, *once, *log, *timeout)?;
 				}
-				ScriptCommands::Edit { glob } => {
-					command_script.edit(glob.clone());
+				ScriptCommands::New { name, dir } => {
+					command_script.new_script(name, *dir)?;
 				}
-				ScriptCommands::Run { glob, sudo } => {
-					command_script.run(glob.clone(), sudo.clone());
+				ScriptCommands::Lint { name } => {
+					command_script.lint(name.clone())?;
+				}
+				ScriptCommands::History { name } => {
+					command_script.history(name.clone())?;
+				}
+				ScriptCommands::Logs { name } => {
+					command_script.logs(name.clone())?;
+				}
+				ScriptCommands::Graph { dot } => {
+					command_script.graph(*dot)?;
 				}
 			}
 		}
@@ -42,13 +105,70 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
 			match &command {
 				ReconcileCommands::Status {} => {
-					command_reconcile.status();
+					command_reconcile.status()?;
 				}
 				ReconcileCommands::Deploy {} => {
-					command_reconcile.deploy();
+					command_reconcile.deploy()?;
 				}
 				ReconcileCommands::Undeploy {} => {
-					command_reconcile.undeploy();
+					command_reconcile.undeploy()?;
+				}
+				ReconcileCommands::Watch {} => {
+					command_reconcile.watch()?;
+				}
+			}
+		}
+		CliCommands::Repo { command } => {
+			let command_repo = CommandRepo::new(config);
+
+			match command {
+				RepoCommands::Status {} => {
+					command_repo.status()?;
+				}
+				RepoCommands::Pull { yes } => {
+					command_repo.pull(*yes)?;
+				}
+				RepoCommands::Push {} => {
+					command_repo.push()?;
+				}
+				RepoCommands::Commit { message } => {
+					command_repo.commit(message.clone())?;
+				}
+			}
+		}
+		CliCommands::Config { command } => {
+			let command_config = CommandConfig::new(config);
+
+			match command {
+				ConfigCommands::Show {} => {
+					command_config.show();
+				}
+			}
+		}
+		CliCommands::Daemon { command } => {
+			let command_daemon = CommandDaemon::new(config);
+
+			match command {
+				DaemonCommands::Start {} => {
+					command_daemon.start()?;
+				}
+				DaemonCommands::Status {} => {
+					command_daemon.request("status")?;
+				}
+				DaemonCommands::Deploy {} => {
+					command_daemon.request("deploy")?;
+				}
+				DaemonCommands::Reload {} => {
+					command_daemon.request("reload")?;
+				}
+				DaemonCommands::Pause {} => {
+					command_daemon.request("pause")?;
+				}
+				DaemonCommands::Resume {} => {
+					command_daemon.request("resume")?;
+				}
+				DaemonCommands::Stop {} => {
+					command_daemon.request("stop")?;
 				}
 			}
 		}
@@ -57,10 +177,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
 			match command {
 				InternalCommands::StartWatcher {} => {
-					command_internal.start_watcher();
+					command_internal.start_watcher()?;
 				}
 				InternalCommands::FindMan { command_line } => {
-					command_internal.find_man();
+					command_internal.find_man(command_line.clone());
 				}
 				InternalCommands::Generate {} => {
 					command_internal.generate();
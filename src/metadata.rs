@@ -0,0 +1,412 @@
+//! Metadata in the header comment of a script
+//!
+//! The header is the block of comment lines, starting with `#` or `//`, at the
+//! top of a script, after the shebang. Lines in the form `key: value` set
+//! metadata, and other lines describe the script:
+//!
+//! ```sh
+//! #!/usr/bin/env bash
+//!
+//! # Install Neovim from the package manager
+//! #
+//! # tags: editor, cli
+//! # order: 20
+//! # requires-sudo: true
+//! # os: linux, macos
+//! # arguments: version [channel]
+//! # requires: install-rustup
+//! # timeout: 10m
+//! ```
+
+use std::{fmt, fs, path::Path, time::Duration};
+
+use crate::error::{Error, Result};
+
+/// The operating systems a script can be restricted to, as in
+/// `std::env::consts::OS`
+pub const KNOWN_OS: [&str; 6] = ["linux", "macos", "windows", "freebsd", "openbsd", "netbsd"];
+
+/// An argument that a script accepts
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScriptArgument {
+	/// The name of the argument
+	pub name: String,
+	/// Whether the argument can be left out, written as `[name]`
+	pub optional: bool,
+}
+
+impl fmt::Display for ScriptArgument {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.optional {
+			true => write!(f, "[{}]", self.name),
+			false => write!(f, "{}", self.name),
+		}
+	}
+}
+
+/// The metadata of a script
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScriptInfo {
+	/// The `description` key, or else the first paragraph of the header
+	pub description: Option<String>,
+	/// Words to group scripts by
+	pub tags: Vec<String>,
+	/// Where the script goes when running every script in a category
+	pub order: Option<i32>,
+	/// Whether the script must run as root
+	pub requires_sudo: bool,
+	/// The operating systems the script runs on. Empty if it runs on any
+	pub os: Vec<String>,
+	/// The arguments the script accepts
+	pub arguments: Vec<ScriptArgument>,
+	/// The scripts that must run before this one
+	pub requires: Vec<String>,
+	/// How long the script may run before it is stopped
+	pub timeout: Option<Duration>,
+}
+
+/// A problem with a line of the header
+#[derive(Debug, PartialEq, Eq)]
+pub struct MetadataError {
+	/// The line number, starting at 1
+	pub line: usize,
+	/// What is wrong with the line
+	pub message: String,
+}
+
+/// Parse a duration like `90`, `90s`, `10m`, or `2h`. A number without a unit
+/// is in seconds
+pub fn parse_duration(value: &str) -> std::result::Result<Duration, String> {
+	let error = || {
+		format!(
+			"invalid duration '{}', expected one like 90s, 10m, or 2h",
+			value
+		)
+	};
+	let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+		Some(i) => value.split_at(i),
+		None => (value, "s"),
+	};
+	let number: u64 = number.parse().map_err(|_| error())?;
+	let seconds = match unit {
+		"s" => Some(number),
+		"m" => number.checked_mul(60),
+		"h" => number.checked_mul(60 * 60),
+		_ => return Err(error()),
+	}
+	.ok_or_else(error)?;
+	match seconds {
+		0 => Err(format!("duration '{}' must be more than zero", value)),
+		_ => Ok(Duration::from_secs(seconds)),
+	}
+}
+
+/// Format a duration in the largest unit that `parse_duration` reads it back
+/// exactly in
+pub fn format_duration(duration: Duration) -> String {
+	match duration.as_secs() {
+		0 => format!("{}ms", duration.as_millis()),
+		seconds if seconds % 3600 == 0 => format!("{}h", seconds / 3600),
+		seconds if seconds % 60 == 0 => format!("{}m", seconds / 60),
+		seconds => format!("{}s", seconds),
+	}
+}
+
+fn split_list(value: &str) -> Vec<String> {
+	value
+		.split(|c: char| c == ',' || c.is_whitespace())
+		.filter(|item| !item.is_empty())
+		.map(String::from)
+		.collect()
+}
+
+impl ScriptInfo {
+	/// Parse the header of a script
+	pub fn parse(content: &str) -> std::result::Result<ScriptInfo, MetadataError> {
+		let mut info = ScriptInfo::default();
+		let mut seen: Vec<&str> = vec![];
+		let mut paragraph: Vec<&str> = vec![];
+		let mut paragraph_done = false;
+
+		for (i, line) in content.lines().enumerate() {
+			if i == 0 && line.starts_with("#!") {
+				continue;
+			}
+			// Blank lines may come before the header, but end it otherwise
+			let line = line.trim();
+			let Some(comment) = line.strip_prefix('#').or_else(|| line.strip_prefix("//")) else {
+				match line.trim().is_empty() && seen.is_empty() && paragraph.is_empty() {
+					true => continue,
+					false => break,
+				}
+			};
+			let comment = comment.trim();
+			let error = |message: String| MetadataError {
+				line: i + 1,
+				message,
+			};
+
+			let (key, value) = match comment.split_once(':') {
+				Some((key, value)) if Self::is_key(key.trim()) => (key.trim(), value.trim()),
+				_ => {
+					if comment.is_empty() {
+						paragraph_done |= !paragraph.is_empty();
+					} else if !paragraph_done && !comment.starts_with("shellcheck ") {
+						paragraph.push(comment);
+					}
+					continue;
+				}
+			};
+
+			if seen.contains(&key) {
+				return Err(error(format!("'{}' is set more than once", key)));
+			}
+			seen.push(key);
+
+			match key {
+				"description" => info.description = Some(String::from(value)),
+				"tags" => info.tags = split_list(value),
+				"requires" => info.requires = split_list(value),
+				"timeout" => info.timeout = Some(parse_duration(value).map_err(error)?),
+				"order" => {
+					info.order =
+						Some(value.parse().map_err(|_| {
+							error(format!("order must be a whole number, not '{}'", value))
+						})?)
+				}
+				"requires-sudo" => {
+					info.requires_sudo = match value {
+						"true" | "yes" => true,
+						"false" | "no" => false,
+						_ => {
+							return Err(error(format!(
+								"requires-sudo must be 'true' or 'false', not '{}'",
+								value
+							)))
+						}
+					}
+				}
+				"os" => {
+					info.os = split_list(value);
+					if let Some(os) = info.os.iter().find(|os| !KNOWN_OS.contains(&os.as_str())) {
+						return Err(error(format!(
+							"unknown os '{}', expected one of {}",
+							os,
+							KNOWN_OS.join(", ")
+						)));
+					}
+				}
+				"arguments" => {
+					for word in value.split_whitespace() {
+						let argument = match word.strip_prefix('[').and_then(|w| w.strip_suffix(']')) {
+							Some(name) => ScriptArgument {
+								name: String::from(name),
+								optional: true,
+							},
+							None => ScriptArgument {
+								name: String::from(word),
+								optional: false,
+							},
+						};
+						if info.arguments.last().is_some_and(|last| last.optional) && !argument.optional {
+							return Err(error(format!(
+								"required argument '{}' comes after an optional one",
+								argument.name
+							)));
+						}
+						info.arguments.push(argument);
+					}
+				}
+				_ => unreachable!(),
+			}
+		}
+
+		if info.description.is_none() && !paragraph.is_empty() {
+			info.description = Some(paragraph.join(" "));
+		}
+
+		Ok(info)
+	}
+
+	fn is_key(key: &str) -> bool {
+		matches!(
+			key,
+			"description"
+				| "tags" | "order"
+				| "requires-sudo"
+				| "os" | "arguments"
+				| "requires"
+				| "timeout"
+		)
+	}
+
+	/// Check that `args` match the declared arguments. Any arguments are
+	/// allowed if none are declared
+	pub fn check_arguments(&self, args: &[String]) -> std::result::Result<(), String> {
+		if self.arguments.is_empty() {
+			return Ok(());
+		}
+
+		let required = self.arguments.iter().filter(|arg| !arg.optional).count();
+		if args.len() < required || args.len() > self.arguments.len() {
+			let usage: Vec<String> = self.arguments.iter().map(|arg| arg.to_string()).collect();
+			return Err(format!(
+				"expected arguments '{}', but got {}",
+				usage.join(" "),
+				args.len()
+			));
+		}
+
+		Ok(())
+	}
+
+	/// Whether the script runs on the current operating system
+	pub fn supports_current_os(&self) -> bool {
+		self.os.is_empty() || self.os.iter().any(|os| os == std::env::consts::OS)
+	}
+}
+
+/// Read the metadata of the script at `path`
+pub fn read_script_info(path: &Path) -> Result<ScriptInfo> {
+	let content = fs::read(path).map_err(|err| Error::io(path, err))?;
+
+	ScriptInfo::parse(&String::from_utf8_lossy(&content)).map_err(|err| Error::Metadata {
+		path: path.to_path_buf(),
+		line: err.line,
+		message: err.message,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::{format_duration, parse_duration, MetadataError, ScriptArgument, ScriptInfo};
+
+	#[test]
+	fn it_parses_the_header() {
+		let info = ScriptInfo::parse(
+			"#!/usr/bin/env bash
+
+# Install Neovim from
+# the package manager
+#
+# More details
+# tags: editor, cli
+# order: 20
+# requires-sudo: yes
+# os: linux macos
+# arguments: version [channel]
+# requires: install-rustup, util.sh
+# timeout: 10m
+
+# description: not part of the header
+echo woof
+",
+		)
+		.unwrap();
+
+		assert_eq!(
+			info,
+			ScriptInfo {
+				description: Some(String::from("Install Neovim from the package manager")),
+				tags: vec![String::from("editor"), String::from("cli")],
+				order: Some(20),
+				requires_sudo: true,
+				os: vec![String::from("linux"), String::from("macos")],
+				arguments: vec![
+					ScriptArgument {
+						name: String::from("version"),
+						optional: false
+					},
+					ScriptArgument {
+						name: String::from("channel"),
+						optional: true
+					},
+				],
+				requires: vec![String::from("install-rustup"), String::from("util.sh")],
+				timeout: Some(Duration::from_secs(600)),
+			}
+		);
+
+		assert_eq!(
+			ScriptInfo::parse("echo woof").unwrap(),
+			ScriptInfo::default()
+		);
+		assert_eq!(
+			ScriptInfo::parse("#!/usr/bin/env -S deno run\n// Woof\n// order: 1").unwrap(),
+			ScriptInfo {
+				description: Some(String::from("Woof")),
+				order: Some(1),
+				..ScriptInfo::default()
+			}
+		);
+		assert_eq!(
+			ScriptInfo::parse("# description: woof\n# Note: bark")
+				.unwrap()
+				.description,
+			Some(String::from("woof"))
+		);
+	}
+
+	#[test]
+	fn it_reports_the_line_of_errors() {
+		let error = |content: &str| ScriptInfo::parse(content).unwrap_err();
+
+		assert_eq!(
+			error("#!/bin/sh\n# woof\n# order: first"),
+			MetadataError {
+				line: 3,
+				message: String::from("order must be a whole number, not 'first'")
+			}
+		);
+		assert_eq!(error("# tags: a\n# tags: b").line, 2);
+		assert_eq!(error("# os: plan9").line, 1);
+		assert_eq!(error("# requires-sudo: maybe").line, 1);
+		assert_eq!(error("# arguments: [a] b").line, 1);
+		assert_eq!(error("# timeout: soon").line, 1);
+	}
+
+	#[test]
+	fn it_parses_durations() {
+		assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+		assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+		assert_eq!(parse_duration("10m"), Ok(Duration::from_secs(600)));
+		assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+		assert!(parse_duration("0").is_err());
+		assert!(parse_duration("10d").is_err());
+		assert!(parse_duration("m").is_err());
+		assert!(parse_duration("").is_err());
+		assert!(parse_duration("18446744073709551615h").is_err());
+		assert!(parse_duration("18446744073709551616").is_err());
+
+		assert_eq!(format_duration(Duration::from_secs(90)), "90s");
+		assert_eq!(format_duration(Duration::from_secs(600)), "10m");
+		assert_eq!(format_duration(Duration::from_secs(7200)), "2h");
+		assert_eq!(format_duration(Duration::from_millis(200)), "200ms");
+	}
+
+	#[test]
+	fn it_checks_arguments() {
+		let args = |args: &[&str]| {
+			args
+				.iter()
+				.map(|arg| String::from(*arg))
+				.collect::<Vec<_>>()
+		};
+		let info = ScriptInfo::parse("# arguments: version [channel]").unwrap();
+
+		assert!(info.check_arguments(&args(&["1.0"])).is_ok());
+		assert!(info.check_arguments(&args(&["1.0", "beta"])).is_ok());
+		assert_eq!(
+			info.check_arguments(&args(&[])),
+			Err(String::from(
+				"expected arguments 'version [channel]', but got 0"
+			))
+		);
+		assert!(info.check_arguments(&args(&["1.0", "beta", "x"])).is_err());
+		assert!(ScriptInfo::default()
+			.check_arguments(&args(&["x", "y"]))
+			.is_ok());
+	}
+}
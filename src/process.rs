@@ -0,0 +1,266 @@
+//! Waiting for scripts with a timeout, and stopping them
+//!
+//! A script runs in its own process group, so that everything it starts can
+//! be stopped at once when it times out or `dotfile` is interrupted. When
+//! standard input is a terminal, the group is made the foreground one while
+//! the script runs, so that the script can still read from the terminal and
+//! Ctrl-C reaches it directly. SIGINT and SIGTERM only stop `dotfile` itself
+//! again once the script is done. Elsewhere, scripts are only waited for
+
+use std::{
+	io,
+	process::{Child, Command, ExitStatus},
+	time::Duration,
+};
+#[cfg(unix)]
+use std::{
+	io::IsTerminal,
+	mem,
+	os::unix::process::{CommandExt, ExitStatusExt},
+	ptr,
+	sync::atomic::{AtomicBool, Ordering},
+	thread,
+	time::Instant,
+};
+
+#[cfg(unix)]
+use libc::{SIGINT, SIGTERM};
+
+/// How long a script has to exit after being asked to, before it is killed
+#[cfg(unix)]
+const GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// How often to check whether a script exited
+#[cfg(unix)]
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How a script run ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+	/// The script exited by itself
+	Exited(ExitStatus),
+	/// The script ran longer than its timeout, and was stopped
+	TimedOut(Duration),
+	/// The script was stopped by Ctrl-C, or `dotfile` was asked to stop
+	Interrupted,
+}
+
+impl Outcome {
+	/// Whether the script exited successfully
+	pub fn success(&self) -> bool {
+		matches!(self, Outcome::Exited(status) if status.success())
+	}
+
+	/// The exit code, if the script exited by itself with one
+	pub fn code(&self) -> Option<i32> {
+		match self {
+			Outcome::Exited(status) => status.code(),
+			_ => None,
+		}
+	}
+}
+
+/// Start `command` in a new process group
+#[cfg(unix)]
+pub fn spawn(command: &mut Command) -> io::Result<Child> {
+	command.process_group(0).spawn()
+}
+
+#[cfg(not(unix))]
+pub fn spawn(command: &mut Command) -> io::Result<Child> {
+	command.spawn()
+}
+
+/// Wait for `child`, started with `spawn`, to exit. If it runs longer than
+/// `timeout`, or `dotfile` gets SIGINT or SIGTERM meanwhile, its process group
+/// is asked to stop with SIGTERM, then killed after a grace period
+#[cfg(unix)]
+pub fn wait(child: &mut Child, timeout: Option<Duration>) -> io::Result<Outcome> {
+	let handlers = Handlers::install()?;
+	let foreground = Foreground::give(child.id());
+
+	let start = Instant::now();
+	let result = loop {
+		match child.try_wait() {
+			Ok(Some(status)) => {
+				// Ctrl-C goes to the script in the foreground, not to dotfile
+				break Ok(match status.signal() {
+					Some(SIGINT) => Outcome::Interrupted,
+					_ => Outcome::Exited(status),
+				});
+			}
+			Ok(None) => {}
+			Err(err) => break Err(err),
+		}
+
+		if INTERRUPTED.load(Ordering::Relaxed) {
+			break stop(child).map(|_| Outcome::Interrupted);
+		}
+		if let Some(timeout) = timeout.filter(|timeout| start.elapsed() >= *timeout) {
+			break stop(child).map(|_| Outcome::TimedOut(timeout));
+		}
+		thread::sleep(POLL_INTERVAL);
+	};
+
+	drop(foreground);
+	drop(handlers);
+
+	result
+}
+
+/// Whether SIGINT or SIGTERM arrived while `Handlers` were installed
+#[cfg(unix)]
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn handle_signal(_: libc::c_int) {
+	INTERRUPTED.store(true, Ordering::Relaxed);
+}
+
+/// Handlers for SIGINT and SIGTERM that only set `INTERRUPTED`, until dropped.
+/// The previous handlers are then restored, so that the signals stop
+/// `dotfile` again outside of `wait`
+#[cfg(unix)]
+struct Handlers {
+	previous: Vec<(libc::c_int, libc::sigaction)>,
+}
+
+#[cfg(unix)]
+impl Handlers {
+	fn install() -> io::Result<Handlers> {
+		INTERRUPTED.store(false, Ordering::Relaxed);
+
+		let mut handlers = Handlers { previous: vec![] };
+		for signal in [SIGINT, SIGTERM] {
+			unsafe {
+				let mut action: libc::sigaction = mem::zeroed();
+				action.sa_sigaction = handle_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+				action.sa_flags = libc::SA_RESTART;
+				libc::sigemptyset(&mut action.sa_mask);
+				let mut previous: libc::sigaction = mem::zeroed();
+				if libc::sigaction(signal, &action, &mut previous) != 0 {
+					// Dropping restores the handlers installed so far
+					return Err(io::Error::last_os_error());
+				}
+				handlers.previous.push((signal, previous));
+			}
+		}
+
+		Ok(handlers)
+	}
+}
+
+#[cfg(unix)]
+impl Drop for Handlers {
+	fn drop(&mut self) {
+		for (signal, previous) in &self.previous {
+			unsafe { libc::sigaction(*signal, previous, ptr::null_mut()) };
+		}
+	}
+}
+
+/// Wait for `child` to exit. Scripts cannot be stopped here, so `timeout` is
+/// ignored
+#[cfg(not(unix))]
+pub fn wait(child: &mut Child, _timeout: Option<Duration>) -> io::Result<Outcome> {
+	child.wait().map(Outcome::Exited)
+}
+
+/// Stop the process group of `child`, and wait for `child` to exit
+#[cfg(unix)]
+fn stop(child: &mut Child) -> io::Result<ExitStatus> {
+	let group = child.id() as libc::pid_t;
+	// The group may already be gone, which is fine
+	unsafe { libc::killpg(group, libc::SIGTERM) };
+
+	let start = Instant::now();
+	while start.elapsed() < GRACE_PERIOD {
+		if let Some(status) = child.try_wait()? {
+			// Processes the script started may outlive it
+			unsafe { libc::killpg(group, libc::SIGKILL) };
+			return Ok(status);
+		}
+		thread::sleep(POLL_INTERVAL);
+	}
+
+	unsafe { libc::killpg(group, libc::SIGKILL) };
+	child.wait()
+}
+
+/// The terminal given to the process group of a script, until dropped
+#[cfg(unix)]
+struct Foreground {
+	// The process group that had the terminal
+	previous: libc::pid_t,
+}
+
+#[cfg(unix)]
+impl Foreground {
+	/// Make the process group `group` the foreground one of the terminal on
+	/// standard input, if it is one and `dotfile` has it
+	fn give(group: u32) -> Option<Foreground> {
+		if !io::stdin().is_terminal() {
+			return None;
+		}
+
+		let previous = unsafe { libc::tcgetpgrp(libc::STDIN_FILENO) };
+		if previous != unsafe { libc::getpgrp() } {
+			return None;
+		}
+		match unsafe { libc::tcsetpgrp(libc::STDIN_FILENO, group as libc::pid_t) } {
+			0 => Some(Foreground { previous }),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(unix)]
+impl Drop for Foreground {
+	fn drop(&mut self) {
+		// Taking the terminal back from the background sends SIGTTOU, which
+		// would stop dotfile
+		unsafe {
+			let handler = libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+			libc::tcsetpgrp(libc::STDIN_FILENO, self.previous);
+			libc::signal(libc::SIGTTOU, handler);
+		}
+	}
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+	use std::{process::Command, time::Duration};
+
+	use super::{spawn, wait, Outcome};
+
+	#[test]
+	fn it_stops_scripts_that_time_out() {
+		let mut child = spawn(Command::new("sh").args(["-c", "exit 3"])).unwrap();
+		let outcome = wait(&mut child, Some(Duration::from_secs(10))).unwrap();
+		assert_eq!(outcome.code(), Some(3));
+		assert!(!outcome.success());
+
+		// The previous handler is back after waiting
+		let handler = unsafe {
+			let mut action: libc::sigaction = std::mem::zeroed();
+			libc::sigaction(libc::SIGTERM, std::ptr::null(), &mut action);
+			action.sa_sigaction
+		};
+		assert_eq!(handler, libc::SIG_DFL);
+
+		// The sleep in the background is in the group too, and holds the pipe
+		// open until it is killed
+		let mut child = spawn(
+			Command::new("sh")
+				.args(["-c", "sleep 30 & sleep 30"])
+				.stdout(std::process::Stdio::piped()),
+		)
+		.unwrap();
+		let outcome = wait(&mut child, Some(Duration::from_millis(200))).unwrap();
+		assert_eq!(outcome, Outcome::TimedOut(Duration::from_millis(200)));
+		let mut stdout = child.stdout.take().unwrap();
+		let mut output = String::new();
+		std::io::Read::read_to_string(&mut stdout, &mut output).unwrap();
+		assert_eq!(output, "");
+	}
+}
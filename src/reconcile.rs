@@ -0,0 +1,811 @@
+//! Reconciling the dotfiles listed by `deploy.sh` with the home directory
+
+use std::{
+	env, fs,
+	io::{self, Write},
+	os,
+	path::{Path, PathBuf},
+	process::{Command, Output},
+	sync::mpsc,
+	time::Duration,
+};
+
+use colored::Colorize;
+use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode, DebounceEventResult};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	config::{Config, ConflictPolicy},
+	error::{self, Error},
+	history, util,
+};
+
+/// What to do with each dotfile
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ReconcileAction {
+	/// Print the dotfiles that are not deployed, or are outdated
+	Status,
+	/// Deploy every dotfile
+	Deploy,
+	/// Remove every deployed dotfile
+	Undeploy,
+}
+
+/// How a dotfile is deployed
+#[derive(Clone, PartialEq, Eq)]
+pub enum DotfileEntryOp {
+	/// The target is a symlink to the source
+	Symlink,
+	/// The target is a copy of the source, updated when the source changes
+	Copy,
+}
+
+/// A dotfile listed by `deploy.sh`
+#[derive(Clone)]
+pub struct DotfileEntry {
+	/// How the dotfile is deployed
+	pub op: DotfileEntryOp,
+	/// Name of the repository whose deploy script listed this entry
+	pub repo: String,
+	/// Path in ~/.dotfiles/
+	pub source: PathBuf,
+	/// Path in ~/
+	pub target: PathBuf,
+}
+
+/// Get the deploy script of a dotmgr directory
+pub fn get_deploy_sh(dotmgr_dir: &Path) -> PathBuf {
+	match env::consts::OS {
+		"windows" => dotmgr_dir.join("impl/deploy.ps1"),
+		_ => dotmgr_dir.join("impl/deploy.sh"),
+	}
+}
+
+/// Run the deploy script of a dotmgr directory, capturing its output
+pub fn run_deploy_sh(dotmgr_dir: &Path) -> Result<Output, std::io::Error> {
+	let deploy_sh = get_deploy_sh(dotmgr_dir);
+
+	match env::consts::OS {
+		"windows" => Command::new("pwsh.exe").arg(deploy_sh).output(),
+		_ => Command::new(deploy_sh).output(),
+	}
+}
+
+/// Parse a line printed by the deploy script. Returns `None` for blank lines
+/// and comments
+pub fn parse_dotfile_line(line: &str, repo: &str) -> Result<Option<DotfileEntry>, String> {
+	if line.is_empty() || line.starts_with('#') {
+		return Ok(None);
+	}
+
+	let parts: Vec<&str> = line.split('|').collect();
+
+	if parts.len() != 3 {
+		return Err(String::from("line does not have two pipes"));
+	}
+
+	let op = match parts[0] {
+		"" | "symlink" => DotfileEntryOp::Symlink,
+		"copy" => DotfileEntryOp::Copy,
+		op => {
+			return Err(format!("line has unknown operation '{}'", op));
+		}
+	};
+
+	Ok(Some(DotfileEntry {
+		op,
+		repo: String::from(repo),
+		source: PathBuf::from(parts[1]),
+		target: PathBuf::from(parts[2]),
+	}))
+}
+
+/// Get the dotfiles listed by the deploy script of every repository. When
+/// several repositories list the same target, the later one wins
+pub fn get_dotfile_list(config: &Config) -> error::Result<Vec<DotfileEntry>> {
+	let mut dotfiles: Vec<DotfileEntry> = vec![];
+
+	for repo in &config.repos {
+		let deploy_sh = get_deploy_sh(&repo.dotmgr_dir);
+		let output = run_deploy_sh(&repo.dotmgr_dir).map_err(|err| Error::spawn(&deploy_sh, err))?;
+
+		if !output.status.success() {
+			return Err(Error::CommandFailed {
+				command: deploy_sh,
+				status: output.status,
+				stderr: String::from(String::from_utf8_lossy(&output.stderr)),
+			});
+		}
+
+		for line in String::from_utf8_lossy(&output.stdout).split('\n') {
+			match parse_dotfile_line(line, &repo.name) {
+				Ok(Some(dotfile)) => {
+					match dotfiles.iter_mut().find(|old| old.target == dotfile.target) {
+						Some(old) => *old = dotfile,
+						None => dotfiles.push(dotfile),
+					}
+				}
+				Ok(None) => {}
+				Err(err) => eprintln!("warning: {}: {}", err, line),
+			}
+		}
+	}
+
+	Ok(dotfiles)
+}
+
+type ReconcilerFn = fn(source: PathBuf, target: PathBuf, out: &mut dyn Write) -> error::Result<()>;
+
+struct Reconciler {
+	pub status: ReconcilerFn,
+	pub deploy: ReconcilerFn,
+	pub undeploy: ReconcilerFn,
+}
+
+// fn prettify_path(path: &PathBuf) -> PathBuf {
+// 	let home = dirs::home_dir().unwrap();
+// 	let home = home.to_str().unwrap();
+
+// 	if path.starts_with(&home) {
+// 		let b: String = String::from(path.to_str().unwrap())
+// 			.chars()
+// 			.skip(home.len() + 1)
+// 			.collect();
+// 		PathBuf::from(String::from("~/") + b.as_str())
+// 	} else {
+// 		PathBuf::from(path)
+// 	}
+// }
+
+fn parent_mkdirp(path: PathBuf) -> error::Result<()> {
+	let parent = path.parent().unwrap();
+	fs::create_dir_all(parent).map_err(|err| Error::io(parent, err))
+}
+
+#[cfg(target_os = "windows")]
+fn symlink(original: PathBuf, target: PathBuf) -> error::Result<()> {
+	let result = if original.is_dir() {
+		os::windows::fs::symlink_dir(original, &target)
+	} else {
+		os::windows::fs::symlink_file(original, &target)
+	};
+	result.map_err(|err| Error::io(&target, err))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn symlink(original: PathBuf, target: PathBuf) -> error::Result<()> {
+	os::unix::fs::symlink(original, &target).map_err(|err| Error::io(&target, err))
+}
+
+fn remove(target: &Path) -> error::Result<()> {
+	let result = if target.is_dir() && !target.is_symlink() {
+		fs::remove_dir_all(target)
+	} else {
+		fs::remove_file(target)
+	};
+	result.map_err(|err| Error::io(target, err))
+}
+
+fn unsymlink(out: &mut dyn Write, target: &Path) -> error::Result<()> {
+	if target.is_symlink() {
+		remove(target)?;
+	} else {
+		writeln!(
+			out,
+			"WARNING: Cannot handle path (not symlink): {}",
+			target.display()
+		)
+		.map_err(|err| Error::io(target, err))?;
+	}
+
+	Ok(())
+}
+
+fn copy(source: &Path, target: &Path) -> io::Result<()> {
+	if source.is_dir() {
+		fs::create_dir_all(target)?;
+		for entry in fs::read_dir(source)? {
+			let entry = entry?;
+			copy(&entry.path(), &target.join(entry.file_name()))?;
+		}
+	} else {
+		fs::copy(source, target)?;
+	}
+
+	Ok(())
+}
+
+fn is_copy_outdated(source: &Path, target: &Path) -> bool {
+	if target.is_symlink() || !target.exists() {
+		return true;
+	}
+
+	if source.is_dir() {
+		match fs::read_dir(source) {
+			Ok(entries) => entries
+				.flatten()
+				.any(|entry| is_copy_outdated(&entry.path(), &target.join(entry.file_name()))),
+			Err(_) => true,
+		}
+	} else {
+		match (fs::read(source), fs::read(target)) {
+			(Ok(a), Ok(b)) => a != b,
+			_ => true,
+		}
+	}
+}
+
+fn get_backup_path(target: &Path) -> PathBuf {
+	let mut backup = target.as_os_str().to_owned();
+	backup.push(".bak");
+	PathBuf::from(backup)
+}
+
+/// Move `target` to its backup path. Returns `false`, after warning, if the
+/// backup already exists
+fn backup(target: &Path, out: &mut dyn Write) -> error::Result<bool> {
+	let backup = get_backup_path(target);
+	if backup.exists() {
+		writeln!(
+			out,
+			"WARNING: Skipping path (backup already exists): {}",
+			backup.display()
+		)
+		.map_err(|err| Error::io(&backup, err))?;
+		return Ok(false);
+	}
+
+	fs::rename(target, &backup).map_err(|err| Error::io(target, err))?;
+	Ok(true)
+}
+
+/// A copy that was deployed, with the hash of its content at the time
+#[derive(Clone, Serialize, Deserialize)]
+struct CopyRecord {
+	target: PathBuf,
+	hash: String,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct CopiesFile {
+	#[serde(default)]
+	copy: Vec<CopyRecord>,
+}
+
+/// Get the path of the file recording deployed copies, in the state directory.
+/// It tells copies that were deployed apart from files that were already there
+pub fn get_copies_path() -> PathBuf {
+	util::get_state_dir().join("copies.toml")
+}
+
+/// Read the deployed copies. An invalid file only makes every copy look like a
+/// file that was already there, which is the safe way to be wrong
+fn read_copies() -> Vec<CopyRecord> {
+	let path = get_copies_path();
+	let Ok(content) = fs::read_to_string(&path) else {
+		return vec![];
+	};
+	match toml::from_str::<CopiesFile>(&content) {
+		Ok(file) => file.copy,
+		Err(err) => {
+			eprintln!("warning: {}: {}", path.display(), err);
+			vec![]
+		}
+	}
+}
+
+fn write_copies(copies: Vec<CopyRecord>) -> error::Result<()> {
+	let path = get_copies_path();
+	if let Some(dir) = path.parent() {
+		fs::create_dir_all(dir).map_err(|err| Error::io(dir, err))?;
+	}
+	let content = toml::to_string(&CopiesFile { copy: copies }).unwrap();
+	fs::write(&path, content).map_err(|err| Error::io(&path, err))
+}
+
+/// Hash the content of a file, or of every file in a directory along with
+/// their paths. Returns `None` if it cannot be read
+fn tree_hash(path: &Path) -> Option<String> {
+	fn collect(path: &Path, name: &Path, buf: &mut Vec<u8>) -> io::Result<()> {
+		if path.is_dir() {
+			buf.extend_from_slice(format!("d {}\0", name.display()).as_bytes());
+			let mut entries: Vec<_> = fs::read_dir(path)?.collect::<io::Result<_>>()?;
+			entries.sort_by_key(|entry| entry.file_name());
+			for entry in entries {
+				collect(&entry.path(), &name.join(entry.file_name()), buf)?;
+			}
+		} else {
+			let content = fs::read(path)?;
+			buf.extend_from_slice(format!("f {} {}\0", name.display(), content.len()).as_bytes());
+			buf.extend_from_slice(&content);
+		}
+		Ok(())
+	}
+
+	let mut buf = vec![];
+	collect(path, Path::new(""), &mut buf).ok()?;
+	Some(history::content_hash(&buf))
+}
+
+/// Whether `target` is a copy that was deployed, and not changed since
+fn is_deployed_copy(target: &Path) -> bool {
+	if target.is_symlink() {
+		return false;
+	}
+	let Some(hash) = tree_hash(target) else {
+		return false;
+	};
+	read_copies()
+		.iter()
+		.any(|record| record.target == target && record.hash == hash)
+}
+
+/// Copy `source` to `target`, replacing whatever is there, and record the copy
+fn deploy_copy(source: &Path, target: &Path) -> error::Result<()> {
+	parent_mkdirp(target.to_path_buf())?;
+	if target.exists() || target.is_symlink() {
+		remove(target)?;
+	}
+	copy(source, target).map_err(|err| Error::io(target, err))?;
+
+	let mut copies = read_copies();
+	copies.retain(|record| record.target != target);
+	if let Some(hash) = tree_hash(target) {
+		copies.push(CopyRecord {
+			target: target.to_path_buf(),
+			hash,
+		});
+	}
+	write_copies(copies)
+}
+
+/// Remove a copy of `source` at `target`, unless it was changed since it was
+/// deployed
+fn undeploy_copy(source: &Path, target: &Path, out: &mut dyn Write) -> error::Result<()> {
+	if target.is_symlink() {
+		return unsymlink(out, target);
+	}
+	if !target.exists() {
+		return Ok(());
+	}
+
+	if is_copy_outdated(source, target) && !is_deployed_copy(target) {
+		writeln!(
+			out,
+			"WARNING: Cannot handle path (modified copy): {}",
+			target.display()
+		)
+		.map_err(|err| Error::io(target, err))?;
+		return Ok(());
+	}
+
+	remove(target)?;
+	let mut copies = read_copies();
+	if copies.iter().any(|record| record.target == target) {
+		copies.retain(|record| record.target != target);
+		write_copies(copies)?;
+	}
+	Ok(())
+}
+
+fn print_title(out: &mut dyn Write, target: &Path) -> error::Result<()> {
+	let basename = target.parent().unwrap_or(target).display().to_string();
+	let filename = String::from(target.file_name().unwrap_or_default().to_string_lossy());
+
+	let output = format!(
+		"{}{}{}",
+		basename.white(),
+		String::from(std::path::MAIN_SEPARATOR).dimmed(),
+		filename.blue()
+	);
+	writeln!(out, "👉 {}", output.as_str()).map_err(|err| Error::io(target, err))
+}
+
+fn print_fixable(out: &mut dyn Write, target: &Path, fixable: bool) -> error::Result<()> {
+	let fixable = if fixable { "yes" } else { "no" };
+	writeln!(out, "  => {} {}", "fixable:".dimmed(), fixable).map_err(|err| Error::io(target, err))
+}
+
+fn print_conflict(out: &mut dyn Write, target: &Path) -> error::Result<()> {
+	writeln!(out, "  => {} target already exists", "conflict:".dimmed())
+		.map_err(|err| Error::io(target, err))
+}
+
+fn print_action(out: &mut dyn Write, target: &Path, action: &str) -> error::Result<()> {
+	writeln!(out, "  => {} {}", "action:".dimmed(), action).map_err(|err| Error::io(target, err))
+}
+
+/// Apply `action` to each dotfile, writing what happened to `out`. Stops at
+/// the first dotfile that fails, returning an `Error::Entry` for it
+pub fn reconcile_dotfiles(
+	dotfiles: &[DotfileEntry],
+	action: ReconcileAction,
+	conflict_policy: ConflictPolicy,
+	out: &mut dyn Write,
+) -> error::Result<()> {
+	// Only worth mentioning when more than one repository is involved
+	let show_repo = dotfiles
+		.iter()
+		.any(|dotfile| dotfile.repo != dotfiles[0].repo);
+
+	let run = |action: &ReconcileAction,
+	           dotfile: &DotfileEntry,
+	           reconciler: Reconciler,
+	           out: &mut dyn Write| {
+		let result = match action {
+			ReconcileAction::Status => {
+				let mut status: Vec<u8> = vec![];
+				let result =
+					(reconciler.status)(dotfile.source.clone(), dotfile.target.clone(), &mut status);
+				out.write_all(&status)
+					.map_err(|err| Error::io(&dotfile.target, err))?;
+				if show_repo && !status.is_empty() {
+					writeln!(out, "  => {} {}", "repo:".dimmed(), dotfile.repo)
+						.map_err(|err| Error::io(&dotfile.target, err))?;
+				}
+				result
+			}
+			ReconcileAction::Deploy => {
+				(reconciler.deploy)(dotfile.source.clone(), dotfile.target.clone(), out)
+			}
+			ReconcileAction::Undeploy => {
+				(reconciler.undeploy)(dotfile.source.clone(), dotfile.target.clone(), out)
+			}
+		};
+
+		result.map_err(|err| Error::Entry {
+			target: dotfile.target.clone(),
+			source: Box::new(err),
+		})
+	};
+
+	for dotfile in dotfiles {
+		if dotfile.op == DotfileEntryOp::Copy {
+			// The target was there before, or was changed since it was copied
+			let is_conflict = || {
+				dotfile.target.exists()
+					&& !dotfile.target.is_symlink()
+					&& is_copy_outdated(&dotfile.source, &dotfile.target)
+					&& !is_deployed_copy(&dotfile.target)
+			};
+
+			if dotfile.source.exists() && is_conflict() {
+				let reconciler = match conflict_policy {
+					ConflictPolicy::Skip => Reconciler {
+						status: |_, target, out| {
+							print_title(out, &target)?;
+							print_conflict(out, &target)?;
+							print_fixable(out, &target, false)?;
+							Ok(())
+						},
+						deploy: |_, target, out| {
+							writeln!(
+								out,
+								"WARNING: Skipping path (already exists): {}",
+								target.display()
+							)
+							.map_err(|err| Error::io(&target, err))?;
+							Ok(())
+						},
+						undeploy: |source, target, out| undeploy_copy(&source, &target, out),
+					},
+					ConflictPolicy::Backup => Reconciler {
+						status: |_, target, out| {
+							print_title(out, &target)?;
+							print_conflict(out, &target)?;
+							print_fixable(out, &target, true)?;
+							Ok(())
+						},
+						deploy: |source, target, out| match backup(&target, out)? {
+							true => deploy_copy(&source, &target),
+							false => Ok(()),
+						},
+						undeploy: |source, target, out| undeploy_copy(&source, &target, out),
+					},
+					ConflictPolicy::Overwrite => Reconciler {
+						status: |_, target, out| {
+							print_title(out, &target)?;
+							print_conflict(out, &target)?;
+							print_fixable(out, &target, true)?;
+							Ok(())
+						},
+						deploy: |source, target, _out| deploy_copy(&source, &target),
+						undeploy: |source, target, out| undeploy_copy(&source, &target, out),
+					},
+				};
+
+				run(&action, dotfile, reconciler, out)?;
+			} else if dotfile.source.exists() {
+				run(
+					&action,
+					dotfile,
+					Reconciler {
+						status: |source, target, out| {
+							if is_copy_outdated(&source, &target) {
+								print_title(out, &target)?;
+								print_fixable(out, &target, true)?;
+							}
+							Ok(())
+						},
+						deploy: |source, target, _out| {
+							if is_copy_outdated(&source, &target) {
+								deploy_copy(&source, &target)?;
+							}
+							Ok(())
+						},
+						undeploy: |source, target, out| undeploy_copy(&source, &target, out),
+					},
+					out,
+				)?;
+			}
+		} else if dotfile.source.exists() && dotfile.target.exists() && !dotfile.target.is_symlink() {
+			let reconciler = match conflict_policy {
+				ConflictPolicy::Skip => Reconciler {
+					status: |_, target, out| {
+						print_title(out, &target)?;
+						print_conflict(out, &target)?;
+						print_fixable(out, &target, false)?;
+						Ok(())
+					},
+					deploy: |_, target, out| {
+						writeln!(
+							out,
+							"WARNING: Skipping path (already exists): {}",
+							target.display()
+						)
+						.map_err(|err| Error::io(&target, err))?;
+						Ok(())
+					},
+					undeploy: |_, target, out| unsymlink(out, &target),
+				},
+				ConflictPolicy::Backup => Reconciler {
+					status: |_, target, out| {
+						print_title(out, &target)?;
+						print_conflict(out, &target)?;
+						print_fixable(out, &target, true)?;
+						Ok(())
+					},
+					deploy: |source, target, out| match backup(&target, out)? {
+						true => symlink(source, target),
+						false => Ok(()),
+					},
+					undeploy: |_, target, out| unsymlink(out, &target),
+				},
+				ConflictPolicy::Overwrite => Reconciler {
+					status: |_, target, out| {
+						print_title(out, &target)?;
+						print_conflict(out, &target)?;
+						print_fixable(out, &target, true)?;
+						Ok(())
+					},
+					deploy: |source, target, _out| {
+						remove(&target)?;
+						symlink(source, target)
+					},
+					undeploy: |_, target, out| unsymlink(out, &target),
+				},
+			};
+
+			run(&action, dotfile, reconciler, out)?;
+		} else if dotfile.source.is_file() {
+			if dotfile.target.is_symlink() {
+				run(
+					&action,
+					dotfile,
+					Reconciler {
+						status: |_source, target, out| {
+							print_title(out, &target)?;
+							print_fixable(out, &target, true)?;
+							Ok(())
+						},
+						deploy: |source, target, _out| {
+							parent_mkdirp(target.clone())?;
+							remove(&target)?;
+							symlink(source, target)
+						},
+						undeploy: |_, target, out| unsymlink(out, &target),
+					},
+					out,
+				)?;
+			} else if !dotfile.target.exists() {
+				run(
+					&action,
+					dotfile,
+					Reconciler {
+						status: |_, target, out| {
+							print_title(out, &target)?;
+							print_fixable(out, &target, true)?;
+							Ok(())
+						},
+						deploy: |source, target, _out| {
+							parent_mkdirp(target.clone())?;
+							symlink(source, target)
+						},
+						undeploy: |_, target, out| unsymlink(out, &target),
+					},
+					out,
+				)?;
+			}
+		} else if dotfile.source.is_dir() {
+			if dotfile.target.is_symlink() {
+				run(
+					&action,
+					dotfile,
+					Reconciler {
+						status: |_source, target, out| {
+							print_title(out, &target)?;
+							print_fixable(out, &target, true)?;
+							Ok(())
+						},
+						deploy: |source, target, _out| {
+							parent_mkdirp(target.clone())?;
+							remove(&target)?;
+							symlink(source, target)
+						},
+						undeploy: |_, target, out| unsymlink(out, &target),
+					},
+					out,
+				)?;
+			} else if !dotfile.target.exists() {
+				run(
+					&action,
+					dotfile,
+					Reconciler {
+						status: |_source, target, out| {
+							print_title(out, &target)?;
+							print_fixable(out, &target, true)?;
+							Ok(())
+						},
+						deploy: |source, target, _out| {
+							parent_mkdirp(target.clone())?;
+							symlink(source, target)
+						},
+						undeploy: |_, target, out| unsymlink(out, &target),
+					},
+					out,
+				)?;
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Watch the dotfiles directory of every repository, and its dotmgr directory
+/// when it is elsewhere, calling `on_change` with the paths that changed. Only
+/// returns if the watcher fails to start
+pub fn watch_dotfiles_dir(
+	config: &Config,
+	mut on_change: impl FnMut(&[PathBuf]),
+) -> error::Result<()> {
+	let (tx, rx) = mpsc::channel::<DebounceEventResult>();
+	let mut debouncer =
+		new_debouncer(Duration::from_millis(500), None, tx).map_err(|err| Error::Watch {
+			message: err.to_string(),
+		})?;
+	let mut dirs: Vec<PathBuf> = vec![];
+	for repo in &config.repos {
+		dirs.push(canonicalize(&repo.dotfiles_dir));
+		let dotmgr_dir = canonicalize(&repo.dotmgr_dir);
+		if !dotmgr_dir.starts_with(canonicalize(&repo.dotfiles_dir)) {
+			dirs.push(dotmgr_dir);
+		}
+	}
+	for dir in &dirs {
+		debouncer
+			.watcher()
+			.watch(dir, RecursiveMode::Recursive)
+			.map_err(|err| Error::Watch {
+				message: format!("{}: {}", dir.display(), err),
+			})?;
+	}
+
+	for result in rx {
+		let changed_paths: Vec<PathBuf> = match result {
+			Ok(events) => events.into_iter().map(|event| event.path).collect(),
+			Err(errors) => {
+				for error in errors {
+					log::error!("watch: {:?}", error);
+				}
+				continue;
+			}
+		};
+
+		for path in &changed_paths {
+			log::info!("changed: {}", path.display());
+		}
+
+		on_change(&changed_paths);
+	}
+
+	Ok(())
+}
+
+/// Get the canonical form of `path`, or `path` itself if it does not exist
+fn canonicalize(path: &Path) -> PathBuf {
+	path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Deploy what a change to the dotfiles directories affects: when a
+/// `deploy.sh` changed, new and changed dotfiles are deployed and removed ones
+/// undeployed; and copies whose source changed are recopied
+pub fn apply_changes(
+	config: &Config,
+	dotfile_list: &mut Vec<DotfileEntry>,
+	changed_paths: &[PathBuf],
+	out: &mut dyn Write,
+) -> error::Result<()> {
+	let mut to_deploy: Vec<DotfileEntry> = vec![];
+	let mut to_undeploy: Vec<DotfileEntry> = vec![];
+
+	let changed_paths: Vec<PathBuf> = changed_paths
+		.iter()
+		.map(|path| canonicalize(path))
+		.collect();
+	let deploy_sh = config
+		.repos
+		.iter()
+		.map(|repo| get_deploy_sh(&repo.dotmgr_dir))
+		.find(|deploy_sh| changed_paths.contains(&canonicalize(deploy_sh)));
+	if let Some(deploy_sh) = deploy_sh {
+		let new_dotfile_list = get_dotfile_list(config)?;
+		print_title(out, &deploy_sh)?;
+		print_action(out, &deploy_sh, "reloaded dotfile list")?;
+
+		for dotfile in &new_dotfile_list {
+			let is_unchanged = dotfile_list.iter().any(|old| {
+				old.target == dotfile.target && old.source == dotfile.source && old.op == dotfile.op
+			});
+			if !is_unchanged {
+				to_deploy.push(dotfile.clone());
+			}
+		}
+		for dotfile in dotfile_list.iter() {
+			if !new_dotfile_list
+				.iter()
+				.any(|entry| entry.target == dotfile.target)
+			{
+				to_undeploy.push(dotfile.clone());
+			}
+		}
+		*dotfile_list = new_dotfile_list;
+	}
+
+	for dotfile in dotfile_list.iter() {
+		if dotfile.op != DotfileEntryOp::Copy
+			|| to_deploy.iter().any(|entry| entry.target == dotfile.target)
+		{
+			continue;
+		}
+
+		let source = canonicalize(&dotfile.source);
+		if changed_paths.iter().any(|path| path.starts_with(&source)) {
+			to_deploy.push(dotfile.clone());
+		}
+	}
+
+	for dotfile in &to_undeploy {
+		print_title(out, &dotfile.target)?;
+		print_action(out, &dotfile.target, "remove")?;
+	}
+	reconcile_dotfiles(
+		&to_undeploy,
+		ReconcileAction::Undeploy,
+		config.conflict_policy,
+		out,
+	)?;
+
+	for dotfile in &to_deploy {
+		print_title(out, &dotfile.target)?;
+		match dotfile.op {
+			DotfileEntryOp::Symlink => print_action(out, &dotfile.target, "symlink")?,
+			DotfileEntryOp::Copy => print_action(out, &dotfile.target, "copy")?,
+		}
+	}
+	reconcile_dotfiles(
+		&to_deploy,
+		ReconcileAction::Deploy,
+		config.conflict_policy,
+		out,
+	)
+}
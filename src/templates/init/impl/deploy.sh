@@ -0,0 +1,11 @@
+#!/usr/bin/env bash
+
+# Print the dotfiles to deploy, one per line, in the form 'op|source|target'.
+# The 'op' is either 'symlink' or 'copy'. Lines starting with '#' are ignored
+
+set -eo pipefail
+
+dotfiles_dir=${DOTMGR_DOTFILES_DIR:-$HOME/.dotfiles}
+
+# printf '%s\n' "symlink|$dotfiles_dir/user/.bashrc|$HOME/.bashrc"
+# printf '%s\n' "copy|$dotfiles_dir/user/.gitconfig|$HOME/.gitconfig"
@@ -0,0 +1,28 @@
+#!/usr/bin/env bash
+
+# This is called by dotfile to run a script. The environment printed by
+# 'environment.sh' is already set
+#   $1: The path of the script to run
+#   $2: A colon-separated list of files in 'util/' to source
+#   $3...: The arguments to pass to the script, given after '--'
+
+set -eo pipefail
+
+main() {
+	local script="$1"
+	local sources="$2"
+	shift 2
+
+	local source=
+	local IFS=':'
+	for source in $sources; do
+		# shellcheck disable=SC1090
+		source "$source"
+	done
+	unset IFS
+
+	# shellcheck disable=SC1090
+	source "$script" "$@"
+}
+
+main "$@"
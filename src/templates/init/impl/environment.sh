@@ -0,0 +1,14 @@
+#!/usr/bin/env bash
+
+# Print the environment that every script runs with, one 'KEY=VALUE' per
+# line, quoted like in a shell. Lines starting with '#' are ignored
+
+set -eo pipefail
+
+# Print a variable, in single quotes so that any value is read back as it is
+print_var() {
+	printf "%s='%s'\n" "$1" "${2//\'/\'\\\'\'}"
+}
+
+print_var XDG_CONFIG_HOME "${XDG_CONFIG_HOME:-$HOME/.config}"
+print_var XDG_DATA_HOME "${XDG_DATA_HOME:-$HOME/.local/share}"
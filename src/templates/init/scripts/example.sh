@@ -0,0 +1,15 @@
+#!/usr/bin/env bash
+
+# An example script that checks that Git is installed
+#
+# Run it with 'dotfile script run example'. Create more categories of scripts
+# in 'scripts-<category>/' and select them with '-c'
+#
+# tags: example
+# order: 10
+
+if iscmd 'git'; then
+	log "Git is installed"
+else
+	die "Git is not installed"
+fi
@@ -0,0 +1,17 @@
+# shellcheck shell=bash
+
+# Every '.sh' file in 'util/' is sourced before a script runs, so functions
+# defined here can be used by any script
+
+log() {
+	printf '%s\n' "$*"
+}
+
+die() {
+	printf '%s\n' "Error: $*" >&2
+	exit 1
+}
+
+iscmd() {
+	command -v "$1" &>/dev/null
+}
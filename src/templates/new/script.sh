@@ -0,0 +1,8 @@
+#!/usr/bin/env bash
+
+# Describe what {{name}} does
+#
+# tags:
+# requires-sudo: false
+
+log "Running {{name}}"
@@ -0,0 +1,59 @@
+//! Helpers shared by the tests of the crate
+
+use std::{
+	env, fs,
+	ops::Deref,
+	path::{Path, PathBuf},
+	process,
+	sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::config::{DotfilesRepo, MAIN_REPO_NAME};
+
+/// An empty temporary directory, removed when dropped
+pub struct TempDir {
+	path: PathBuf,
+}
+
+impl TempDir {
+	/// Create a directory named after `name`, which is unique to each test
+	pub fn new(name: &str) -> TempDir {
+		static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+		let path = env::temp_dir().join(format!(
+			"dotfile-{}-test-{}-{}",
+			name,
+			process::id(),
+			COUNT.fetch_add(1, Ordering::SeqCst)
+		));
+		let _ = fs::remove_dir_all(&path);
+		fs::create_dir_all(&path).unwrap();
+
+		TempDir { path }
+	}
+}
+
+impl Deref for TempDir {
+	type Target = Path;
+
+	fn deref(&self) -> &Path {
+		&self.path
+	}
+}
+
+impl Drop for TempDir {
+	fn drop(&mut self) {
+		let _ = fs::remove_dir_all(&self.path);
+	}
+}
+
+/// The main repository, with its dotfiles, OS, and dotmgr directories all at
+/// `dir`
+pub fn repo(dir: &Path) -> DotfilesRepo {
+	DotfilesRepo {
+		name: String::from(MAIN_REPO_NAME),
+		dotfiles_dir: dir.to_path_buf(),
+		os_dir: dir.to_path_buf(),
+		dotmgr_dir: dir.to_path_buf(),
+	}
+}
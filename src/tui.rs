@@ -3,21 +3,24 @@ use crossterm::{
 	execute,
 	terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use glob::glob;
-use std::{
-	io::{self},
-	path::PathBuf,
-	process::exit,
-};
+use std::io::{self};
 use tui::{
 	backend::{Backend, CrosstermBackend},
 	layout::{Constraint, Direction, Layout},
 	style::{Modifier, Style},
-	text::Span,
-	widgets::{Block, Borders, List, ListItem},
+	text::{Span, Spans},
+	widgets::{Block, Borders, List, ListItem, Tabs},
 	Frame, Terminal,
 };
 
+use fox_dotfile::{
+	config::Config,
+	error::{Error, Result},
+	util::Script,
+};
+
+use crate::commands::{daemon_request, DaemonResponse};
+
 enum Selected {
 	Left,
 	Right,
@@ -25,63 +28,78 @@ enum Selected {
 
 struct App {
 	active_pane: Selected,
-	path_dir: PathBuf,
-	dirnames: Vec<String>,
+	// The title and scripts of each tab
+	tabs: Vec<(String, Vec<Script>)>,
+	selected_tab_index: usize,
 	selected_file_index: usize,
+	viewer_title: String,
 	viewer_content: String,
+	// The configuration of the daemon that `s` and `d` ask
+	config: Config,
 }
 
 impl App {
-	fn new(dir: PathBuf, dirnames: Vec<String>) -> App {
+	fn new(tabs: Vec<(String, Vec<Script>)>, selected_tab_index: usize, config: &Config) -> App {
 		App {
 			active_pane: Selected::Left,
-			path_dir: dir,
-			dirnames,
+			tabs,
+			selected_tab_index,
 			selected_file_index: 0,
+			viewer_title: String::default(),
 			viewer_content: String::default(),
+			config: config.clone(),
 		}
 	}
+
+	fn scripts(&self) -> &[Script] {
+		&self.tabs[self.selected_tab_index].1
+	}
+
+	fn select_tab(&mut self, index: usize) {
+		self.selected_tab_index = index;
+		self.selected_file_index = 0;
+		update_viewer(self);
+	}
 }
 
-pub fn choose_script(dir: PathBuf) -> PathBuf {
+/// Choose a script with the TUI, from tabs of scripts with a title each,
+/// starting at tab `selected`. The daemon for `config` can be asked for
+/// the status of the dotfiles, or to deploy them, meanwhile. Returns `None` if
+/// the TUI was closed without choosing a script
+pub fn choose_script(
+	tabs: Vec<(String, Vec<Script>)>,
+	selected: usize,
+	config: &Config,
+) -> Result<Option<Script>> {
+	if tabs.iter().all(|(_, scripts)| scripts.is_empty()) {
+		return Err(Error::script("No scripts found"));
+	}
+
 	// setup terminal
-	terminal::enable_raw_mode().unwrap();
+	terminal::enable_raw_mode().map_err(Error::Terminal)?;
 	let mut stdout = io::stdout();
-	execute!(stdout, EnterAlternateScreen, EnableMouseCapture).unwrap();
+	execute!(stdout, EnterAlternateScreen, EnableMouseCapture).map_err(Error::Terminal)?;
 	let backend = CrosstermBackend::new(stdout);
-	let mut terminal = Terminal::new(backend).unwrap();
+	let mut terminal = Terminal::new(backend).map_err(Error::Terminal)?;
 
 	// run
-	let app_dir = dir.join("*.sh");
-	let files: Vec<String> = glob(app_dir.to_str().unwrap())
-		.unwrap()
-		.map(|item| {
-			let pbuf = item.unwrap();
-			let dirname = pbuf.file_name().unwrap();
-			String::from(dirname.to_str().unwrap())
-		})
-		.collect();
-	let app = App::new(dir, files);
+	let app = App::new(tabs, selected, config);
 	let res = run_app(&mut terminal, app);
-	let option = res.unwrap();
 
-	// restore terminal
-	terminal::disable_raw_mode().unwrap();
+	// restore terminal, even if the app failed
+	terminal::disable_raw_mode().map_err(Error::Terminal)?;
 	execute!(
 		terminal.backend_mut(),
 		LeaveAlternateScreen,
 		DisableMouseCapture
 	)
-	.unwrap();
-	terminal.show_cursor().unwrap();
+	.map_err(Error::Terminal)?;
+	terminal.show_cursor().map_err(Error::Terminal)?;
 
-	match option {
-		Some(val) => val,
-		None => exit(0),
-	}
+	res.map_err(Error::Terminal)
 }
 
-fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<Option<PathBuf>> {
+fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<Option<Script>> {
 	update_viewer(&mut app);
 
 	loop {
@@ -97,11 +115,19 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<O
 					update_viewer(&mut app)
 				}
 				KeyCode::Char('j') => {
-					if app.selected_file_index < app.dirnames.len() - 1 {
+					if app.selected_file_index + 1 < app.scripts().len() {
 						app.selected_file_index += 1
 					}
 					update_viewer(&mut app)
 				}
+				KeyCode::Char('h') | KeyCode::Left if app.selected_tab_index > 0 => {
+					app.select_tab(app.selected_tab_index - 1)
+				}
+				KeyCode::Char('l') | KeyCode::Right if app.selected_tab_index + 1 < app.tabs.len() => {
+					app.select_tab(app.selected_tab_index + 1)
+				}
+				KeyCode::Char('s') => show_daemon_response(&mut app, "status"),
+				KeyCode::Char('d') => show_daemon_response(&mut app, "deploy"),
 				KeyCode::Tab => {
 					app.active_pane = match app.active_pane {
 						Selected::Left => Selected::Right,
@@ -109,9 +135,9 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<O
 					}
 				}
 				KeyCode::Enter => {
-					let dirname = app.dirnames[app.selected_file_index].as_str();
-					let fullpath = PathBuf::from(app.path_dir.to_str().unwrap()).join(dirname);
-					return Ok(Option::Some(fullpath));
+					if let Some(script) = app.scripts().get(app.selected_file_index) {
+						return Ok(Option::Some(script.clone()));
+					}
 				}
 				_ => {}
 			}
@@ -120,30 +146,49 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<O
 }
 
 fn ui<B: Backend>(f: &mut Frame<B>, app: &App) {
+	let mut area = f.size();
+
+	// With one tab, its title is the title of the list
+	if app.tabs.len() > 1 {
+		let rows = Layout::default()
+			.direction(Direction::Vertical)
+			.constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+			.split(area);
+		let titles: Vec<Spans> = app
+			.tabs
+			.iter()
+			.map(|(title, _)| Spans::from(title.as_str()))
+			.collect();
+		let tabs = Tabs::new(titles)
+			.block(Block::default().borders(Borders::ALL).title("Categories"))
+			.select(app.selected_tab_index)
+			.highlight_style(Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED));
+		f.render_widget(tabs, rows[0]);
+		area = rows[1];
+	}
+
 	let chunks = Layout::default()
 		.direction(Direction::Horizontal)
 		.constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
-		.split(f.size());
+		.split(area);
 
 	{
-		let app_dir = String::from(app.path_dir.to_str().unwrap()) + "/*.sh";
-		let files: Vec<ListItem> = glob(&app_dir)
-			.unwrap()
+		let files: Vec<ListItem> = app
+			.scripts()
+			.iter()
 			.enumerate()
-			.map(|(i, item)| {
-				let pbuf = item.unwrap();
-				let dirname = pbuf.file_name().unwrap();
-				let dirname_str = dirname.to_str().unwrap().to_owned();
+			.map(|(i, script)| {
+				let id = script.id.clone();
 				let span = if app.selected_file_index == i {
-					Span::styled(dirname_str, Style::default().add_modifier(Modifier::BOLD))
+					Span::styled(id, Style::default().add_modifier(Modifier::BOLD))
 				} else {
-					Span::from(dirname_str)
+					Span::from(id)
 				};
-				return ListItem::new(span);
+				ListItem::new(span)
 			})
 			.collect();
 
-		let title = "Files";
+		let title = app.tabs[app.selected_tab_index].0.as_str();
 		let span = match app.active_pane {
 			Selected::Left => Span::styled(title, Style::default().add_modifier(Modifier::BOLD)),
 			_ => Span::from(title),
@@ -154,7 +199,7 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &App) {
 	}
 
 	{
-		let title = "Viewer";
+		let title = app.viewer_title.as_str();
 		let span = match app.active_pane {
 			Selected::Right => Span::styled(title, Style::default().add_modifier(Modifier::BOLD)),
 			_ => Span::from(title),
@@ -166,8 +211,56 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &App) {
 }
 
 fn update_viewer(app: &mut App) {
-	let dirname = app.dirnames[app.selected_file_index].as_str();
-	let fullpath = PathBuf::from(app.path_dir.to_str().unwrap()).join(dirname);
-	// let content = fs::read_to_string(fullpath).unwrap();
-	app.viewer_content = String::from(fullpath.to_str().unwrap())
+	app.viewer_title = String::from("Viewer");
+	let Some(script) = app.scripts().get(app.selected_file_index) else {
+		app.viewer_content = String::from("No scripts in this category");
+		return;
+	};
+	let mut lines = vec![script.path.display().to_string()];
+
+	match script.info() {
+		Ok(info) => {
+			lines.push(String::new());
+			if let Some(description) = info.description {
+				lines.push(description);
+				lines.push(String::new());
+			}
+			if !info.tags.is_empty() {
+				lines.push(format!("tags: {}", info.tags.join(", ")));
+			}
+			if let Some(order) = info.order {
+				lines.push(format!("order: {}", order));
+			}
+			if info.requires_sudo {
+				lines.push(String::from("requires sudo: yes"));
+			}
+			if !info.os.is_empty() {
+				lines.push(format!("os: {}", info.os.join(", ")));
+			}
+			if !info.arguments.is_empty() {
+				let arguments: Vec<String> = info.arguments.iter().map(|a| a.to_string()).collect();
+				lines.push(format!("arguments: {}", arguments.join(" ")));
+			}
+		}
+		Err(err) => lines.push(format!("error: {}", err)),
+	}
+
+	app.viewer_content = lines.join("\n");
+}
+
+/// Send `request` to the daemon, and show its response in the viewer
+fn show_daemon_response(app: &mut App, request: &str) {
+	app.viewer_title = format!("Daemon: {}", request);
+	app.viewer_content = match daemon_request(&app.config, request) {
+		Some(DaemonResponse::Ok(output)) => output,
+		Some(DaemonResponse::Failed { output, message }) => format!("{}error: {}", output, message),
+		Some(DaemonResponse::OtherConfig) => format!(
+			"The daemon for {} runs with another configuration. Restart it with this one",
+			app.config.dotfiles_dir.display()
+		),
+		None => format!(
+			"The daemon is not running for {}. Start it with `dotfile daemon start`",
+			app.config.dotfiles_dir.display()
+		),
+	};
 }
@@ -1,108 +1,366 @@
+//! Script discovery, and the environment scripts run in
+
 use std::{
 	collections::HashMap,
-	env, fs, os,
-	path::PathBuf,
-	process::{exit, Command, Stdio},
+	env, fs,
+	path::{Path, PathBuf},
+	process::{Command, Stdio},
 };
 
-use colored::Colorize;
-use glob::glob;
+use crate::{
+	category::CategoryDir,
+	config::DotfilesRepo,
+	environment,
+	error::{Error, Result},
+	fuzzy, history,
+	metadata::{read_script_info, ScriptInfo},
+};
 
-use crate::{cli::ReconcileCommands, config::Config, tui};
+/// A script in the category directory of one of the repositories
+#[derive(Clone)]
+pub struct Script {
+	/// The repository the script is from
+	pub repo: DotfilesRepo,
+	/// The category the script is in
+	pub category: String,
+	/// Name to show and select the script by. When there is more than one
+	/// repository or category, this is namespaced like `repo/category/name`
+	pub id: String,
+	/// Path to the script, or to its directory
+	pub path: PathBuf,
+}
 
-pub fn get_script_exec(dir: PathBuf, glob_pattern: Option<String>) -> PathBuf {
-	match glob_pattern {
-		Some(ref val) => {
-			let s = String::from(format!("{}/*{}*", dir.to_str().unwrap(), val.as_str()));
+/// What runs a script
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Runner {
+	/// The `impl/entrypoint.<ext>` for the extension of the script
+	Entrypoint(PathBuf),
+	/// The interpreter in the shebang of the script, with its arguments
+	Shebang(Vec<String>),
+}
 
-			let mut paths: Vec<PathBuf> = vec![];
-			for result in glob(s.as_str()).unwrap() {
-				paths.push(result.unwrap())
-			}
+impl Script {
+	/// Get the file to run. For a directory, this is the file named `script`
+	/// inside it, with any extension, preferring `script.sh`
+	pub fn file(&self) -> PathBuf {
+		if !self.path.is_dir() {
+			return self.path.clone();
+		}
 
-			if paths.len() > 1 {
-				eprintln!("Your string matches more than one file, try again");
-				exit(1);
-			}
+		let mut files: Vec<PathBuf> = fs::read_dir(&self.path)
+			.into_iter()
+			.flatten()
+			.flatten()
+			.map(|entry| entry.path())
+			.filter(|path| path.file_stem().is_some_and(|stem| stem == "script") && path.is_file())
+			.collect();
+		files.sort();
+		files
+			.iter()
+			.find(|path| path.extension().is_some_and(|ext| ext == "sh"))
+			.or(files.first())
+			.cloned()
+			.unwrap_or_else(|| self.path.join("script.sh"))
+	}
 
-			let mut script = paths.first().unwrap().to_owned();
-			if String::from(script.to_str().unwrap()).is_empty() {
-				eprintln!("The found script is empty");
-				exit(1);
-			}
+	/// Read the metadata in the header of the script
+	pub fn info(&self) -> Result<ScriptInfo> {
+		read_script_info(&self.file())
+	}
 
-			if script.is_dir() {
-				script = script.join("script.sh");
-			}
+	/// Hash the content of the script, as recorded in the history of runs
+	pub fn hash(&self) -> Result<String> {
+		let file = self.file();
+		let content = fs::read(&file).map_err(|err| Error::io(&file, err))?;
+		Ok(history::content_hash(&content))
+	}
 
-			env::set_current_dir(script.parent().unwrap()).unwrap();
+	/// Find what runs the script: the entrypoint for its extension, or else
+	/// the interpreter in its shebang
+	pub fn runner(&self) -> Option<Runner> {
+		let file = self.file();
+		if !file.is_file() {
+			return None;
+		}
 
-			if !script.exists() {
-				eprintln!("Script does not exist: {}", script.to_str().unwrap());
-				exit(1);
+		if let Some(ext) = file.extension().and_then(|ext| ext.to_str()) {
+			let entrypoint = get_entrypoint(&self.repo.dotmgr_dir, ext);
+			if entrypoint.is_file() {
+				return Some(Runner::Entrypoint(entrypoint));
 			}
-
-			PathBuf::from(script)
 		}
-		None => {
-			return tui::choose_script(dir);
+
+		let content = fs::read(&file).ok()?;
+		let first_line = String::from_utf8_lossy(&content).lines().next()?.to_owned();
+		let interpreter: Vec<String> = first_line
+			.strip_prefix("#!")?
+			.split_whitespace()
+			.map(String::from)
+			.collect();
+		match interpreter.is_empty() {
+			true => None,
+			false => Some(Runner::Shebang(interpreter)),
 		}
 	}
-}
 
-pub fn get_entrypoint_sh(dotmgr_dir: &str) -> PathBuf {
-	return PathBuf::from(dotmgr_dir).join("impl/entrypoint.sh");
-}
+	/// Whether the script can be run, by an entrypoint or its shebang
+	pub fn is_runnable(&self) -> bool {
+		self.runner().is_some()
+	}
 
-fn get_environment_sh(dotmgr_dir: &str) -> PathBuf {
-	return PathBuf::from(dotmgr_dir).join("impl/environment.sh");
+	/// Get the position of the script when running every script: its `order`
+	/// metadata, or else the number its name starts with, like `10` in
+	/// `10-git.sh`
+	pub fn order(&self, info: &ScriptInfo) -> Option<i32> {
+		info.order.or_else(|| {
+			let name = self.path.file_name()?.to_str()?;
+			let digits: String = name.chars().take_while(|c| c.is_ascii_digit()).collect();
+			digits.parse().ok()
+		})
+	}
 }
 
-pub fn get_environment(
-	config: &Config,
-) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
-	let mut map = HashMap::new();
+/// Get every script in the given category directories. The id of a script is
+/// namespaced by its repository and category when there is more than one of
+/// them, like `repo/category/name`
+pub fn get_scripts(category_dirs: &[CategoryDir]) -> Vec<Script> {
+	let mut scripts = vec![];
+	let many_repos = category_dirs
+		.iter()
+		.any(|dir| dir.repo.name != category_dirs[0].repo.name);
+	let many_categories = category_dirs
+		.iter()
+		.any(|dir| dir.category != category_dirs[0].category);
+
+	for category_dir in category_dirs {
+		let mut entries: Vec<PathBuf> = match fs::read_dir(&category_dir.dir) {
+			Ok(val) => val.flatten().map(|entry| entry.path()).collect(),
+			Err(_) => continue,
+		};
+		entries.sort();
+
+		for path in entries {
+			// Names that are not Unicode cannot be selected, so they are skipped
+			let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+				continue;
+			};
+			let name = String::from(name);
+			// The README describes the category
+			if name.starts_with('.') || name.starts_with("README") {
+				continue;
+			}
 
-	let environment_script = get_environment_sh(config.dotmgr_dir.to_str().unwrap());
-	let child = Command::new(environment_script)
-		.stdout(Stdio::piped())
-		.spawn()
-		.expect("failed to execute child");
+			let mut id = vec![];
+			if many_repos {
+				id.push(category_dir.repo.name.as_str());
+			}
+			if many_categories {
+				id.push(category_dir.category.as_str());
+			}
+			id.push(&name);
+			scripts.push(Script {
+				repo: category_dir.repo.clone(),
+				category: category_dir.category.clone(),
+				id: id.join("/"),
+				path,
+			});
+		}
+	}
 
-	let output = child.wait_with_output().expect("failed to wait on child");
+	scripts
+}
 
-	for line in String::from_utf8(output.stdout.clone())?.lines() {
-		if line.starts_with("#") {
-			continue;
-		}
+/// The scripts that a query selects
+pub enum ScriptMatch {
+	/// One script, resolved like `resolve_script`
+	One(Script),
+	/// Several scripts, best first
+	Many(Vec<Script>),
+}
 
-		match line.find("=") {
-			Some(val) => {
-				let key = line[..val].trim().replace('"', "");
-				let value = line[val + 1..].trim().replace('"', "");
+/// Find the scripts that `query` selects. A script whose name, with or without
+/// the extension, is the query is selected alone. Otherwise the scripts are
+/// ranked by how well their name fuzzy matches the query, then by whether
+/// their description contains it, and the best one is selected alone if it
+/// ranks strictly higher than the rest. A query like `repo/name` or
+/// `category/name` only searches that repository or category. Fails with
+/// suggestions if nothing matches
+pub fn find_scripts(category_dirs: &[CategoryDir], query: &str) -> Result<ScriptMatch> {
+	let mut category_dirs = category_dirs.to_vec();
+	let mut query = query;
+	while let Some((prefix, rest)) = query.split_once('/') {
+		let matches = |dir: &CategoryDir| dir.repo.name == prefix || dir.category == prefix;
+		if !category_dirs.iter().any(matches) {
+			break;
+		}
+		category_dirs.retain(matches);
+		query = rest;
+	}
+	let scripts = get_scripts(&category_dirs);
+	let name = |script: &Script| {
+		let name = script.path.file_name().unwrap_or_default();
+		String::from(name.to_string_lossy())
+	};
+	let stem = |script: &Script| {
+		let stem = script.path.file_stem().unwrap_or_default();
+		String::from(stem.to_string_lossy())
+	};
+
+	let mut exact: Vec<Script> = scripts
+		.iter()
+		.filter(|script| {
+			name(script).eq_ignore_ascii_case(query) || stem(script).eq_ignore_ascii_case(query)
+		})
+		.cloned()
+		.collect();
+	if exact.len() == 1 {
+		return Ok(ScriptMatch::One(resolve_script(exact.remove(0))?));
+	} else if exact.len() > 1 {
+		return Ok(ScriptMatch::Many(exact));
+	}
 
-				map.insert(String::from(key), String::from(value));
+	// Matches by name come before matches by description
+	let lowercase_query = query.to_lowercase();
+	let mut ranked: Vec<((bool, i32), Script)> = scripts
+		.iter()
+		.filter_map(|script| {
+			if let Some(score) = fuzzy::score(query, &name(script)) {
+				return Some(((true, score), script.clone()));
+			}
+			let description = script.info().ok()?.description?;
+			match description.to_lowercase().contains(&lowercase_query) {
+				true => Some(((false, 0), script.clone())),
+				false => None,
 			}
-			None => {}
+		})
+		.collect();
+	ranked.sort_by_key(|(rank, _)| std::cmp::Reverse(*rank));
+
+	match ranked.len() {
+		0 => {
+			let mut suggestions: Vec<(usize, String)> = scripts
+				.iter()
+				.map(|script| (fuzzy::distance(query, &stem(script)), script.id.clone()))
+				.filter(|(distance, _)| *distance <= (query.chars().count() / 3).max(2))
+				.collect();
+			suggestions.sort();
+			let suggestions: Vec<String> = suggestions.into_iter().take(3).map(|(_, id)| id).collect();
+
+			Err(Error::script(match suggestions.is_empty() {
+				true => format!("'{}' does not match any script", query),
+				false => format!(
+					"'{}' does not match any script. Did you mean {}?",
+					query,
+					suggestions.join(", ")
+				),
+			}))
 		}
+		// Ties are left to the user
+		n if n == 1 || ranked[0].0 > ranked[1].0 => {
+			Ok(ScriptMatch::One(resolve_script(ranked.remove(0).1)?))
+		}
+		_ => Ok(ScriptMatch::Many(
+			ranked.into_iter().map(|(_, script)| script).collect(),
+		)),
 	}
+}
+
+/// Resolve a directory to the `script.sh` inside it, and check that the
+/// script exists
+pub fn resolve_script(mut script: Script) -> Result<Script> {
+	script.path = script.file();
 
-	Ok(map)
+	if !script.path.exists() {
+		return Err(Error::script(format!(
+			"Script does not exist: {}",
+			script.path.display()
+		)));
+	}
+
+	Ok(script)
+}
+
+/// Get the script that runs the scripts with extension `ext` of a dotmgr
+/// directory
+pub fn get_entrypoint(dotmgr_dir: &Path, ext: &str) -> PathBuf {
+	dotmgr_dir.join(format!("impl/entrypoint.{}", ext))
 }
 
-pub fn get_sources(dotmgr_dir: &str) -> String {
-	let mut paths: Vec<String> = Vec::new();
+/// Get the script that runs shell scripts of a dotmgr directory
+pub fn get_entrypoint_sh(dotmgr_dir: &Path) -> PathBuf {
+	get_entrypoint(dotmgr_dir, "sh")
+}
 
-	let dir = PathBuf::from(dotmgr_dir).join("util");
+/// Get the script that prints the environment of a dotmgr directory
+pub fn get_environment_sh(dotmgr_dir: &Path) -> PathBuf {
+	dotmgr_dir.join("impl/environment.sh")
+}
 
-	for result in glob(format!("{}/*.sh", dir.to_str().unwrap()).as_str()).unwrap() {
-		let s = String::from(result.unwrap().to_str().unwrap());
-		paths.push(s);
+/// Run `environment.sh` of a dotmgr directory, and collect the variables it
+/// prints, as read by `environment::parse`. A variable printed more than once
+/// has its last value. Fails if the script exits unsuccessfully, or prints
+/// something invalid
+pub fn get_environment(dotmgr_dir: &Path) -> Result<HashMap<String, String>> {
+	let environment_script = get_environment_sh(dotmgr_dir);
+	let output = Command::new(&environment_script)
+		.stderr(Stdio::inherit())
+		.output()
+		.map_err(|err| Error::spawn(&environment_script, err))?;
+	if !output.status.success() {
+		return Err(Error::CommandFailed {
+			command: environment_script,
+			status: output.status,
+			stderr: String::new(),
+		});
 	}
 
-	paths.join(":")
+	let vars = environment::parse(&String::from_utf8_lossy(&output.stdout)).map_err(|err| {
+		Error::Environment {
+			path: environment_script,
+			line: err.line,
+			message: err.message,
+		}
+	})?;
+
+	Ok(vars.into_iter().collect())
+}
+
+/// Get the utility scripts with extension `ext` of a dotmgr directory, in
+/// alphabetical order
+pub fn get_sources(dotmgr_dir: &Path, ext: &str) -> Vec<PathBuf> {
+	let mut paths: Vec<PathBuf> = fs::read_dir(dotmgr_dir.join("util"))
+		.into_iter()
+		.flatten()
+		.flatten()
+		.map(|entry| entry.path())
+		.filter(|path| path.extension().is_some_and(|val| val == ext) && path.is_file())
+		.collect();
+	paths.sort();
+
+	paths
 }
 
+/// Get the directory to keep state in, like the history of script runs. This
+/// is `$XDG_STATE_HOME/dotfile`, falling back to the local data directory
+pub fn get_state_dir() -> PathBuf {
+	dirs::state_dir()
+		.or_else(dirs::data_local_dir)
+		.unwrap_or_else(env::temp_dir)
+		.join("dotfile")
+}
+
+/// Get the name of this machine from `hostname`, falling back to `unknown`
+pub fn get_hostname() -> String {
+	Command::new("hostname")
+		.output()
+		.ok()
+		.filter(|output| output.status.success())
+		.map(|output| String::from(String::from_utf8_lossy(&output.stdout).trim()))
+		.filter(|hostname| !hostname.is_empty())
+		.unwrap_or_else(|| String::from("unknown"))
+}
+
+/// Get the editor from `VISUAL` or `EDITOR`, falling back to `vi`
 pub fn get_editor() -> String {
 	match env::var("VISUAL") {
 		Ok(val) => val,
@@ -113,6 +371,8 @@ pub fn get_editor() -> String {
 	}
 }
 
+/// Get `bat` if it is installed, else the pager from `PAGER`, falling back to
+/// `less`
 pub fn get_pager() -> String {
 	if does_command_exist("bat", "--help") {
 		String::from("bat")
@@ -124,6 +384,8 @@ pub fn get_pager() -> String {
 	}
 }
 
+/// Get the man page name of a command line, like `git-status` for
+/// `git status`
 pub fn find_man(command_line: String) -> String {
 	let mut line = String::from(command_line.trim());
 	if line.starts_with("sudo") {
@@ -140,7 +402,7 @@ pub fn find_man(command_line: String) -> String {
 		}
 		Some('\'') => {
 			if let Some(i) = chars.clone().position(|s| s == '\'') {
-				if chars.clone().skip(i + 1).next().unwrap_or(' ') == ' ' {
+				if chars.clone().nth(i + 1).unwrap_or(' ') == ' ' {
 					let part1 = chars.clone().take(i).collect::<String>();
 					let part2 = chars.clone().skip(i + 1).collect::<String>();
 					line = part1 + &part2;
@@ -149,7 +411,7 @@ pub fn find_man(command_line: String) -> String {
 		}
 		Some('\"') => {
 			if let Some(i) = chars.clone().position(|c| c == '\"') {
-				if chars.clone().skip(i + 1).next().unwrap_or(' ') == ' ' {
+				if chars.clone().nth(i + 1).unwrap_or(' ') == ' ' {
 					let part1 = chars.clone().take(i).collect::<String>();
 					let part2 = chars.clone().skip(i + 1).collect::<String>();
 					line = part1 + &part2;
@@ -168,12 +430,171 @@ pub fn find_man(command_line: String) -> String {
 
 	line = line.replace(" ", "-");
 
-	return line;
+	line
+}
+
+/// Whether running a command with `help_flag` succeeds in starting it
+pub fn does_command_exist(command_name: &str, help_flag: &str) -> bool {
+	let mut command = Command::new(command_name);
+	command.arg(help_flag);
+	command.stdin(Stdio::null());
+	command.stdout(Stdio::null());
+	command.stderr(Stdio::null());
+
+	if let Ok(mut child) = command.spawn() {
+		child.wait().unwrap();
+		true
+	} else {
+		false
+	}
+}
+
+/// Find an executable in the `PATH`
+pub fn find_command(command_name: &str) -> Option<PathBuf> {
+	let command_path = PathBuf::from(command_name);
+	if command_path.components().count() > 1 {
+		return Some(command_path).filter(|path| is_executable(path));
+	}
+
+	env::split_paths(&env::var_os("PATH")?)
+		.map(|dir| dir.join(command_name))
+		.find(|path| is_executable(path))
+}
+
+/// Find the program to run commands as root with: `sudo`, `doas`, or `pkexec`
+pub fn get_sudo_command() -> Option<PathBuf> {
+	["sudo", "doas", "pkexec"]
+		.into_iter()
+		.find_map(find_command)
+}
+
+/// Whether the process runs as root
+#[cfg(unix)]
+pub fn is_root() -> bool {
+	Command::new("id")
+		.arg("-u")
+		.output()
+		.is_ok_and(|output| String::from_utf8_lossy(&output.stdout).trim() == "0")
+}
+
+#[cfg(not(unix))]
+pub fn is_root() -> bool {
+	false
+}
+
+/// Whether the path is a file with an executable bit set
+#[cfg(unix)]
+pub fn is_executable(path: &Path) -> bool {
+	use std::os::unix::fs::PermissionsExt;
+
+	match fs::metadata(path) {
+		Ok(metadata) => metadata.is_file() && metadata.permissions().mode() & 0o111 != 0,
+		Err(_) => false,
+	}
+}
+
+#[cfg(not(unix))]
+pub fn is_executable(path: &Path) -> bool {
+	path.is_file()
 }
 
 #[cfg(test)]
 mod tests {
-	use super::find_man;
+	use std::fs;
+
+	use super::{find_man, find_scripts, get_scripts, get_sources, Runner, Script, ScriptMatch};
+	use crate::{
+		category::CategoryDir,
+		test_util::{self, TempDir},
+	};
+
+	#[test]
+	fn it_finds_the_runner_of_scripts() {
+		let dir = TempDir::new("util");
+		fs::create_dir_all(dir.join("impl")).unwrap();
+		fs::create_dir_all(dir.join("scripts/dir")).unwrap();
+		fs::write(dir.join("impl/entrypoint.py"), "").unwrap();
+		fs::write(dir.join("scripts/a.py"), "print('woof')").unwrap();
+		fs::write(dir.join("scripts/b.rb"), "#!/usr/bin/env ruby\nputs 1").unwrap();
+		fs::write(dir.join("scripts/c.txt"), "woof").unwrap();
+		fs::write(dir.join("scripts/dir/script.rb"), "#!/bin/ruby").unwrap();
+
+		let script = |name: &str| Script {
+			repo: test_util::repo(&dir),
+			category: String::from("default"),
+			id: String::from(name),
+			path: dir.join("scripts").join(name),
+		};
+		assert_eq!(
+			script("a.py").runner(),
+			Some(Runner::Entrypoint(dir.join("impl/entrypoint.py")))
+		);
+		assert_eq!(
+			script("b.rb").runner(),
+			Some(Runner::Shebang(vec![
+				String::from("/usr/bin/env"),
+				String::from("ruby")
+			]))
+		);
+		assert_eq!(script("c.txt").runner(), None);
+		assert_eq!(script("dir").file(), dir.join("scripts/dir/script.rb"));
+		assert!(script("dir").is_runnable());
+	}
+
+	#[test]
+	fn it_selects_the_best_match_alone() {
+		let dir = TempDir::new("util");
+		fs::create_dir_all(dir.join("scripts")).unwrap();
+		for name in ["nvim.sh", "env-vim.sh", "git-a.sh", "git-b.sh"] {
+			fs::write(dir.join("scripts").join(name), "").unwrap();
+		}
+		let category_dirs = [CategoryDir {
+			repo: test_util::repo(&dir),
+			category: String::from("default"),
+			dir: dir.join("scripts"),
+		}];
+		let ids = |query: &str| match find_scripts(&category_dirs, query).unwrap() {
+			ScriptMatch::One(script) => vec![script.id],
+			ScriptMatch::Many(scripts) => scripts.into_iter().map(|script| script.id).collect(),
+		};
+
+		assert_eq!(ids("nvi"), ["nvim.sh"]);
+		assert_eq!(ids("git"), ["git-a.sh", "git-b.sh"]);
+		assert_eq!(ids("git-b"), ["git-b.sh"]);
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn it_skips_scripts_with_names_that_are_not_utf8() {
+		use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+		let dir = TempDir::new("util");
+		fs::create_dir_all(dir.join("scripts")).unwrap();
+		fs::create_dir_all(dir.join("util")).unwrap();
+		fs::write(dir.join("scripts/a.sh"), "").unwrap();
+		fs::write(dir.join("scripts").join(OsStr::from_bytes(b"b\xff.sh")), "").unwrap();
+		fs::write(dir.join("util/b.sh"), "").unwrap();
+		fs::write(dir.join("util").join(OsStr::from_bytes(b"a\xff.sh")), "").unwrap();
+		fs::write(dir.join("util/c.py"), "").unwrap();
+		let category_dirs = [CategoryDir {
+			repo: test_util::repo(&dir),
+			category: String::from("default"),
+			dir: dir.join("scripts"),
+		}];
+
+		let ids: Vec<String> = get_scripts(&category_dirs)
+			.into_iter()
+			.map(|script| script.id)
+			.collect();
+		assert_eq!(ids, ["a.sh"]);
+		assert_eq!(
+			get_sources(&dir, "sh"),
+			[
+				dir.join("util").join(OsStr::from_bytes(b"a\xff.sh")),
+				dir.join("util/b.sh")
+			]
+		);
+	}
 
 	#[test]
 	fn it_works() {
@@ -196,18 +617,3 @@ mod tests {
 		assert_eq!(find_man(String::from("kubectl status")), "kubectl-status");
 	}
 }
-
-pub fn does_command_exist(command_name: &str, help_flag: &str) -> bool {
-	let mut command = Command::new(command_name);
-	command.arg(help_flag);
-	command.stdin(Stdio::null());
-	command.stdout(Stdio::null());
-	command.stderr(Stdio::null());
-
-	if let Ok(mut child) = command.spawn() {
-		child.wait().unwrap();
-		true
-	} else {
-		false
-	}
-}
@@ -0,0 +1,108 @@
+use std::{
+	env, fs,
+	os::unix::fs::PermissionsExt,
+	path::{Path, PathBuf},
+	process::{self, Command},
+	sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A temporary home directory with a dotfiles repository in it, so that
+/// `dotfile` runs without touching the real one
+pub struct Harness {
+	pub root: PathBuf,
+}
+
+#[allow(dead_code)]
+impl Harness {
+	pub fn new() -> Self {
+		static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+		let root = env::temp_dir().join(format!(
+			"dotfile-test-{}-{}",
+			process::id(),
+			COUNT.fetch_add(1, Ordering::SeqCst)
+		));
+		let _ = fs::remove_dir_all(&root);
+		fs::create_dir_all(root.join("home/.dotfiles/os/unix/dotmgr/impl")).unwrap();
+		fs::create_dir_all(root.join("runtime")).unwrap();
+
+		Self { root }
+	}
+
+	pub fn home(&self) -> PathBuf {
+		self.root.join("home")
+	}
+
+	pub fn dotfiles(&self) -> PathBuf {
+		self.home().join(".dotfiles")
+	}
+
+	pub fn dotmgr(&self) -> PathBuf {
+		self.dotfiles().join("os/unix/dotmgr")
+	}
+
+	pub fn write(&self, path: &Path, content: &str) {
+		fs::create_dir_all(path.parent().unwrap()).unwrap();
+		fs::write(path, content).unwrap();
+	}
+
+	pub fn write_executable(&self, path: &Path, content: &str) {
+		self.write(path, content);
+		fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+	}
+
+	/// Write a deploy script that prints the given `op|source|target` lines
+	pub fn deploy_sh(&self, lines: &[String]) {
+		self.deploy_sh_in(&self.dotmgr(), lines);
+	}
+
+	/// Like `deploy_sh`, for the dotmgr directory `dotmgr` of another
+	/// repository
+	pub fn deploy_sh_in(&self, dotmgr: &Path, lines: &[String]) {
+		self.write_executable(
+			&dotmgr.join("impl/deploy.sh"),
+			&format!("#!/bin/sh\ncat <<'EOF'\n{}\nEOF\n", lines.join("\n")),
+		);
+	}
+
+	/// A command that runs `dotfile` in the temporary home directory
+	pub fn command(&self, args: &[&str]) -> Command {
+		let mut command = Command::new(env!("CARGO_BIN_EXE_dotfile"));
+		command
+			.args(args)
+			.env_clear()
+			.env("PATH", env::var_os("PATH").unwrap())
+			.env("HOME", self.home())
+			.env("XDG_CONFIG_HOME", self.home().join(".config"))
+			.env("XDG_RUNTIME_DIR", self.root.join("runtime"))
+			.env("NO_COLOR", "1");
+		command
+	}
+
+	/// Run `dotfile`, and return its stdout. Panics if it fails
+	pub fn run(&self, args: &[&str]) -> String {
+		let output = self.command(args).output().unwrap();
+		assert!(
+			output.status.success(),
+			"dotfile {:?} failed: {}",
+			args,
+			String::from_utf8_lossy(&output.stderr)
+		);
+
+		String::from(String::from_utf8_lossy(&output.stdout))
+	}
+
+	/// Run `dotfile`, and return its stderr. Panics if it succeeds
+	pub fn run_failing(&self, args: &[&str]) -> String {
+		let output = self.command(args).output().unwrap();
+		assert!(!output.status.success(), "dotfile {:?} succeeded", args);
+
+		String::from(String::from_utf8_lossy(&output.stderr))
+	}
+}
+
+impl Drop for Harness {
+	fn drop(&mut self) {
+		let _ = fs::remove_dir_all(&self.root);
+	}
+}
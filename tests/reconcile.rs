@@ -0,0 +1,588 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::{
+	fs,
+	os::unix::fs::symlink,
+	path::Path,
+	process::Stdio,
+	thread,
+	time::{Duration, Instant},
+};
+
+use common::Harness;
+
+fn line(op: &str, source: &Path, target: &Path) -> String {
+	format!(
+		"{}|{}|{}",
+		op,
+		source.to_str().unwrap(),
+		target.to_str().unwrap()
+	)
+}
+
+#[test]
+fn source_file_target_empty() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("f");
+	let target = h.home().join(".config/app/f");
+	h.write(&source, "woof");
+	h.deploy_sh(&[line("symlink", &source, &target)]);
+
+	assert!(h.run(&["reconcile", "status"]).contains("fixable: yes"));
+
+	h.run(&["reconcile", "deploy"]);
+	assert_eq!(fs::read_link(&target).unwrap(), source);
+
+	h.run(&["reconcile", "undeploy"]);
+	assert!(!target.is_symlink());
+	assert!(source.is_file());
+}
+
+#[test]
+fn source_file_target_empty_without_op() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("f");
+	let target = h.home().join(".f");
+	h.write(&source, "woof");
+	h.deploy_sh(&[line("", &source, &target)]);
+
+	h.run(&["reconcile", "deploy"]);
+	assert_eq!(fs::read_link(&target).unwrap(), source);
+}
+
+#[test]
+fn source_file_target_symlink() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("f");
+	let target = h.home().join(".f");
+	h.write(&source, "woof");
+	symlink(h.root.join("elsewhere"), &target).unwrap();
+	h.deploy_sh(&[line("symlink", &source, &target)]);
+
+	assert!(h.run(&["reconcile", "status"]).contains("fixable: yes"));
+
+	h.run(&["reconcile", "deploy"]);
+	assert_eq!(fs::read_link(&target).unwrap(), source);
+
+	h.run(&["reconcile", "undeploy"]);
+	assert!(!target.is_symlink());
+}
+
+#[test]
+fn source_dir_target_empty() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("d");
+	let target = h.home().join(".config/d");
+	h.write(&source.join("f"), "woof");
+	h.deploy_sh(&[line("symlink", &source, &target)]);
+
+	h.run(&["reconcile", "deploy"]);
+	assert_eq!(fs::read_link(&target).unwrap(), source);
+	assert_eq!(fs::read_to_string(target.join("f")).unwrap(), "woof");
+
+	h.run(&["reconcile", "undeploy"]);
+	assert!(!target.exists());
+	assert!(source.join("f").is_file());
+}
+
+#[test]
+fn source_dir_target_symlink() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("d");
+	let target = h.home().join(".d");
+	h.write(&source.join("f"), "woof");
+	symlink(h.root.join("elsewhere"), &target).unwrap();
+	h.deploy_sh(&[line("symlink", &source, &target)]);
+
+	h.run(&["reconcile", "deploy"]);
+	assert_eq!(fs::read_link(&target).unwrap(), source);
+}
+
+#[test]
+fn source_empty() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("missing");
+	let target = h.home().join(".missing");
+	h.deploy_sh(&[line("symlink", &source, &target)]);
+
+	h.run(&["reconcile", "deploy"]);
+	assert!(!target.is_symlink());
+
+	h.run(&["reconcile", "undeploy"]);
+	assert!(!target.is_symlink());
+}
+
+#[test]
+fn source_file_target_file_skip() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("f");
+	let target = h.home().join(".f");
+	h.write(&source, "woof");
+	h.write(&target, "bark");
+	h.deploy_sh(&[line("symlink", &source, &target)]);
+
+	let status = h.run(&["reconcile", "status"]);
+	assert!(status.contains("conflict: target already exists"));
+	assert!(status.contains("fixable: no"));
+
+	assert!(h
+		.run(&["reconcile", "deploy"])
+		.contains("Skipping path (already exists)"));
+	assert_eq!(fs::read_to_string(&target).unwrap(), "bark");
+
+	assert!(h
+		.run(&["reconcile", "undeploy"])
+		.contains("Cannot handle path (not symlink)"));
+	assert_eq!(fs::read_to_string(&target).unwrap(), "bark");
+}
+
+#[test]
+fn source_file_target_file_backup() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("f");
+	let target = h.home().join(".f");
+	h.write(&source, "woof");
+	h.write(&target, "bark");
+	h.deploy_sh(&[line("symlink", &source, &target)]);
+
+	let status = h.run(&["--conflict-policy", "backup", "reconcile", "status"]);
+	assert!(status.contains("fixable: yes"));
+
+	h.run(&["--conflict-policy", "backup", "reconcile", "deploy"]);
+	assert_eq!(fs::read_link(&target).unwrap(), source);
+	assert_eq!(fs::read_to_string(h.home().join(".f.bak")).unwrap(), "bark");
+}
+
+#[test]
+fn source_file_target_file_backup_exists() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("f");
+	let target = h.home().join(".f");
+	h.write(&source, "woof");
+	h.write(&target, "bark");
+	h.write(&h.home().join(".f.bak"), "meow");
+	h.deploy_sh(&[line("symlink", &source, &target)]);
+
+	let output = h.run(&["--conflict-policy", "backup", "reconcile", "deploy"]);
+	assert!(output.contains("Skipping path (backup already exists)"));
+	assert_eq!(fs::read_to_string(&target).unwrap(), "bark");
+	assert_eq!(fs::read_to_string(h.home().join(".f.bak")).unwrap(), "meow");
+}
+
+#[test]
+fn source_file_target_file_overwrite() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("f");
+	let target = h.home().join(".f");
+	h.write(&source, "woof");
+	h.write(&target, "bark");
+	h.deploy_sh(&[line("symlink", &source, &target)]);
+
+	h.run(&["--conflict-policy", "overwrite", "reconcile", "deploy"]);
+	assert_eq!(fs::read_link(&target).unwrap(), source);
+	assert!(!h.home().join(".f.bak").exists());
+}
+
+#[test]
+fn source_dir_target_dir_overwrite() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("d");
+	let target = h.home().join(".d");
+	h.write(&source.join("f"), "woof");
+	h.write(&target.join("g"), "bark");
+	h.deploy_sh(&[line("symlink", &source, &target)]);
+
+	h.run(&["--conflict-policy", "overwrite", "reconcile", "deploy"]);
+	assert_eq!(fs::read_link(&target).unwrap(), source);
+	assert!(!source.join("g").exists());
+}
+
+#[test]
+fn copy_source_file_target_empty() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("f");
+	let target = h.home().join(".config/f");
+	h.write(&source, "woof");
+	h.deploy_sh(&[line("copy", &source, &target)]);
+
+	assert!(h.run(&["reconcile", "status"]).contains("fixable: yes"));
+
+	h.run(&["reconcile", "deploy"]);
+	assert!(!target.is_symlink());
+	assert_eq!(fs::read_to_string(&target).unwrap(), "woof");
+	assert!(!h.run(&["reconcile", "status"]).contains("fixable"));
+
+	h.run(&["reconcile", "undeploy"]);
+	assert!(!target.exists());
+	assert!(source.is_file());
+}
+
+#[test]
+fn copy_source_file_target_outdated() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("f");
+	let target = h.home().join(".f");
+	h.write(&source, "woof");
+	h.deploy_sh(&[line("copy", &source, &target)]);
+	h.run(&["reconcile", "deploy"]);
+
+	h.write(&source, "bark");
+	assert!(h.run(&["reconcile", "status"]).contains("fixable: yes"));
+
+	h.run(&["reconcile", "deploy"]);
+	assert_eq!(fs::read_to_string(&target).unwrap(), "bark");
+}
+
+#[test]
+fn copy_source_file_target_symlink() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("f");
+	let target = h.home().join(".f");
+	h.write(&source, "woof");
+	symlink(&source, &target).unwrap();
+	h.deploy_sh(&[line("copy", &source, &target)]);
+
+	h.run(&["reconcile", "deploy"]);
+	assert!(!target.is_symlink());
+	assert_eq!(fs::read_to_string(&target).unwrap(), "woof");
+}
+
+#[test]
+fn copy_source_file_target_modified() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("f");
+	let target = h.home().join(".f");
+	h.write(&source, "woof");
+	h.deploy_sh(&[line("copy", &source, &target)]);
+	h.run(&["reconcile", "deploy"]);
+
+	h.write(&target, "bark");
+	let output = h.run(&["reconcile", "undeploy"]);
+	assert!(output.contains("Cannot handle path (modified copy)"));
+	assert_eq!(fs::read_to_string(&target).unwrap(), "bark");
+}
+
+#[test]
+fn copy_source_dir_target_empty() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("d");
+	let target = h.home().join(".d");
+	h.write(&source.join("f"), "woof");
+	h.write(&source.join("sub/g"), "bark");
+	h.deploy_sh(&[line("copy", &source, &target)]);
+
+	h.run(&["reconcile", "deploy"]);
+	assert!(!target.is_symlink());
+	assert_eq!(fs::read_to_string(target.join("f")).unwrap(), "woof");
+	assert_eq!(fs::read_to_string(target.join("sub/g")).unwrap(), "bark");
+
+	h.write(&source.join("sub/g"), "meow");
+	h.run(&["reconcile", "deploy"]);
+	assert_eq!(fs::read_to_string(target.join("sub/g")).unwrap(), "meow");
+
+	h.run(&["reconcile", "undeploy"]);
+	assert!(!target.exists());
+}
+
+#[test]
+fn copy_source_empty() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("missing");
+	let target = h.home().join(".missing");
+	h.deploy_sh(&[line("copy", &source, &target)]);
+
+	h.run(&["reconcile", "deploy"]);
+	assert!(!target.exists());
+}
+
+#[test]
+fn source_dir_target_file() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("d");
+	let target = h.home().join(".d");
+	h.write(&source.join("f"), "woof");
+	h.write(&target, "bark");
+	h.deploy_sh(&[line("symlink", &source, &target)]);
+
+	let status = h.run(&["reconcile", "status"]);
+	assert!(status.contains("conflict: target already exists"));
+	assert!(status.contains("fixable: no"));
+
+	h.run(&["reconcile", "deploy"]);
+	assert_eq!(fs::read_to_string(&target).unwrap(), "bark");
+
+	h.run(&["--conflict-policy", "backup", "reconcile", "deploy"]);
+	assert_eq!(fs::read_link(&target).unwrap(), source);
+	assert_eq!(fs::read_to_string(h.home().join(".d.bak")).unwrap(), "bark");
+}
+
+#[test]
+fn source_file_target_dir() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("f");
+	let target = h.home().join(".f");
+	h.write(&source, "woof");
+	h.write(&target.join("g"), "bark");
+	h.deploy_sh(&[line("symlink", &source, &target)]);
+
+	let status = h.run(&["reconcile", "status"]);
+	assert!(status.contains("conflict: target already exists"));
+
+	h.run(&["reconcile", "deploy"]);
+	assert_eq!(fs::read_to_string(target.join("g")).unwrap(), "bark");
+
+	h.run(&["reconcile", "undeploy"]);
+	assert_eq!(fs::read_to_string(target.join("g")).unwrap(), "bark");
+
+	h.run(&["--conflict-policy", "overwrite", "reconcile", "deploy"]);
+	assert_eq!(fs::read_link(&target).unwrap(), source);
+}
+
+#[test]
+fn source_empty_target_file() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("missing");
+	let target = h.home().join(".missing");
+	let copy_target = h.home().join(".missing-copy");
+	h.write(&target, "bark");
+	h.write(&copy_target, "meow");
+	h.deploy_sh(&[
+		line("symlink", &source, &target),
+		line("copy", &source, &copy_target),
+	]);
+
+	assert!(!h.run(&["reconcile", "status"]).contains("fixable"));
+
+	for policy in ["skip", "backup", "overwrite"] {
+		h.run(&["--conflict-policy", policy, "reconcile", "deploy"]);
+		h.run(&["--conflict-policy", policy, "reconcile", "undeploy"]);
+		assert_eq!(fs::read_to_string(&target).unwrap(), "bark");
+		assert_eq!(fs::read_to_string(&copy_target).unwrap(), "meow");
+	}
+	assert!(!h.home().join(".missing.bak").exists());
+	assert!(!h.home().join(".missing-copy.bak").exists());
+}
+
+#[test]
+fn copy_source_file_target_file_skip() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("f");
+	let target = h.home().join(".f");
+	h.write(&source, "woof");
+	h.write(&target, "PRECIOUS");
+	h.deploy_sh(&[line("copy", &source, &target)]);
+
+	let status = h.run(&["reconcile", "status"]);
+	assert!(status.contains("conflict: target already exists"));
+	assert!(status.contains("fixable: no"));
+
+	assert!(h
+		.run(&["reconcile", "deploy"])
+		.contains("Skipping path (already exists)"));
+	assert_eq!(fs::read_to_string(&target).unwrap(), "PRECIOUS");
+
+	h.run(&["reconcile", "undeploy"]);
+	assert_eq!(fs::read_to_string(&target).unwrap(), "PRECIOUS");
+}
+
+#[test]
+fn copy_source_file_target_file_same() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("f");
+	let target = h.home().join(".f");
+	h.write(&source, "woof");
+	h.write(&target, "woof");
+	h.deploy_sh(&[line("copy", &source, &target)]);
+
+	assert!(!h.run(&["reconcile", "status"]).contains("fixable"));
+	h.run(&["reconcile", "deploy"]);
+	assert_eq!(fs::read_to_string(&target).unwrap(), "woof");
+}
+
+#[test]
+fn copy_source_file_target_file_backup() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("f");
+	let target = h.home().join(".f");
+	h.write(&source, "woof");
+	h.write(&target, "PRECIOUS");
+	h.deploy_sh(&[line("copy", &source, &target)]);
+
+	let status = h.run(&["--conflict-policy", "backup", "reconcile", "status"]);
+	assert!(status.contains("fixable: yes"));
+
+	h.run(&["--conflict-policy", "backup", "reconcile", "deploy"]);
+	assert!(!target.is_symlink());
+	assert_eq!(fs::read_to_string(&target).unwrap(), "woof");
+	assert_eq!(
+		fs::read_to_string(h.home().join(".f.bak")).unwrap(),
+		"PRECIOUS"
+	);
+
+	// It is now a deployed copy, so it follows the source
+	h.write(&source, "bark");
+	h.run(&["reconcile", "deploy"]);
+	assert_eq!(fs::read_to_string(&target).unwrap(), "bark");
+}
+
+#[test]
+fn copy_source_file_target_file_overwrite() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("f");
+	let target = h.home().join(".f");
+	h.write(&source, "woof");
+	h.write(&target, "PRECIOUS");
+	h.deploy_sh(&[line("copy", &source, &target)]);
+
+	h.run(&["--conflict-policy", "overwrite", "reconcile", "deploy"]);
+	assert_eq!(fs::read_to_string(&target).unwrap(), "woof");
+	assert!(!h.home().join(".f.bak").exists());
+}
+
+#[test]
+fn copy_source_file_target_modified_copy() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("f");
+	let target = h.home().join(".f");
+	h.write(&source, "woof");
+	h.deploy_sh(&[line("copy", &source, &target)]);
+	h.run(&["reconcile", "deploy"]);
+
+	// A copy changed by hand is no longer replaced
+	h.write(&target, "edited");
+	h.write(&source, "bark");
+	let status = h.run(&["reconcile", "status"]);
+	assert!(status.contains("conflict: target already exists"));
+	h.run(&["reconcile", "deploy"]);
+	assert_eq!(fs::read_to_string(&target).unwrap(), "edited");
+}
+
+#[test]
+fn copy_source_dir_target_file() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("d");
+	let target = h.home().join(".d");
+	h.write(&source.join("f"), "woof");
+	h.write(&target, "PRECIOUS");
+	h.deploy_sh(&[line("copy", &source, &target)]);
+
+	let status = h.run(&["reconcile", "status"]);
+	assert!(status.contains("conflict: target already exists"));
+
+	h.run(&["reconcile", "deploy"]);
+	assert_eq!(fs::read_to_string(&target).unwrap(), "PRECIOUS");
+
+	h.run(&["--conflict-policy", "backup", "reconcile", "deploy"]);
+	assert_eq!(fs::read_to_string(target.join("f")).unwrap(), "woof");
+	assert_eq!(
+		fs::read_to_string(h.home().join(".d.bak")).unwrap(),
+		"PRECIOUS"
+	);
+}
+
+#[test]
+fn copy_source_file_target_dir() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("f");
+	let target = h.home().join(".f");
+	h.write(&source, "woof");
+	h.write(&target.join("g"), "bark");
+	h.deploy_sh(&[line("copy", &source, &target)]);
+
+	assert!(h
+		.run(&["reconcile", "status"])
+		.contains("conflict: target already exists"));
+
+	h.run(&["reconcile", "deploy"]);
+	assert_eq!(fs::read_to_string(target.join("g")).unwrap(), "bark");
+
+	h.run(&["--conflict-policy", "overwrite", "reconcile", "deploy"]);
+	assert_eq!(fs::read_to_string(&target).unwrap(), "woof");
+}
+
+#[test]
+fn two_repos_same_target() {
+	let h = Harness::new();
+	let company = h.home().join("company");
+	let team = h.home().join("team");
+	h.write(
+		&h.home().join(".config/dotfile/config.toml"),
+		"[[repos]]\nname = 'company'\ndotfiles_dir = 'company'\n\n\
+		 [[repos]]\nname = 'team'\ndotfiles_dir = 'team'\n",
+	);
+	let gitconfig = h.home().join(".gitconfig");
+	let vimrc = h.home().join(".vimrc");
+	for (repo, name) in [(&company, "company"), (&team, "team")] {
+		h.write(&repo.join("gitconfig"), name);
+		h.write(&repo.join("vimrc"), name);
+		h.deploy_sh_in(
+			&repo.join("os/unix/dotmgr"),
+			&[
+				line("symlink", &repo.join("gitconfig"), &gitconfig),
+				line("symlink", &repo.join("vimrc"), &vimrc),
+			],
+		);
+	}
+	h.write(&h.dotfiles().join("gitconfig"), "main");
+	h.deploy_sh(&[line("symlink", &h.dotfiles().join("gitconfig"), &gitconfig)]);
+
+	let status = h.run(&["reconcile", "status"]);
+	assert_eq!(status.matches("repo: main").count(), 1, "{status}");
+	assert_eq!(status.matches("repo: team").count(), 1, "{status}");
+	assert!(!status.contains("repo: company"), "{status}");
+
+	// Later repositories win, and the main one is the last
+	h.run(&["reconcile", "deploy"]);
+	assert_eq!(fs::read_to_string(&gitconfig).unwrap(), "main");
+	assert_eq!(fs::read_to_string(&vimrc).unwrap(), "team");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn status_fails_when_output_cannot_be_written() {
+	let h = Harness::new();
+	let source = h.dotfiles().join("f");
+	h.write(&source, "woof");
+	h.deploy_sh(&[line("symlink", &source, &h.home().join(".f"))]);
+
+	let output = h
+		.command(&["reconcile", "status"])
+		.stdout(fs::File::create("/dev/full").unwrap())
+		.output()
+		.unwrap();
+	assert_eq!(output.status.code(), Some(4));
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("No space left on device"), "{stderr}");
+}
+
+#[test]
+fn watch_follows_deploy_sh_outside_dotfiles_dir() {
+	let h = Harness::new();
+	let dotmgr = h.root.join("dotmgr");
+	let source = h.dotfiles().join("f");
+	let old_target = h.home().join(".f");
+	let new_target = h.home().join(".g");
+	h.write(&source, "woof");
+	h.deploy_sh_in(&dotmgr, &[line("symlink", &source, &old_target)]);
+	let dotmgr_arg = dotmgr.to_str().unwrap();
+	h.run(&["--dotmgr-dir", dotmgr_arg, "reconcile", "deploy"]);
+
+	let mut watch = h
+		.command(&["--dotmgr-dir", dotmgr_arg, "reconcile", "watch"])
+		.stdout(Stdio::null())
+		.spawn()
+		.unwrap();
+	thread::sleep(Duration::from_secs(1));
+	h.deploy_sh_in(&dotmgr, &[line("symlink", &source, &new_target)]);
+
+	let deadline = Instant::now() + Duration::from_secs(10);
+	while (old_target.is_symlink() || !new_target.is_symlink()) && Instant::now() < deadline {
+		thread::sleep(Duration::from_millis(100));
+	}
+	watch.kill().unwrap();
+	watch.wait().unwrap();
+	assert!(!old_target.is_symlink());
+	assert_eq!(fs::read_link(&new_target).unwrap(), source);
+}
@@ -0,0 +1,128 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+
+use common::Harness;
+
+/// Write an entrypoint for shell scripts that sources them, and an empty
+/// environment
+fn setup(h: &Harness) {
+	h.write_executable(
+		&h.dotmgr().join("impl/entrypoint.sh"),
+		"#!/bin/sh\nscript=\"$1\"\nshift 2\n. \"$script\" \"$@\"\n",
+	);
+	h.write_executable(&h.dotmgr().join("impl/environment.sh"), "#!/bin/sh\n");
+}
+
+#[test]
+fn run_all_with_directory_script() {
+	let h = Harness::new();
+	setup(&h);
+	let tool = h.dotmgr().join("scripts/10-tool");
+	h.write(&tool.join("script.sh"), "pwd > \"$HOME/tool\"\n");
+	h.write(&tool.join("README.md"), "Not a script\n");
+	h.write(
+		&h.dotmgr().join("scripts/20-after.sh"),
+		"# requires: 10-tool\ncat \"$HOME/tool\" > \"$HOME/after\"\n",
+	);
+
+	let output = h.run(&["script", "run-all"]);
+	assert!(output.contains("10-tool"), "{output}");
+	assert_eq!(output.matches(" ok ").count(), 2, "{output}");
+
+	// The script file runs from its directory
+	let pwd = format!("{}\n", tool.to_str().unwrap());
+	assert_eq!(fs::read_to_string(h.home().join("tool")).unwrap(), pwd);
+	assert_eq!(fs::read_to_string(h.home().join("after")).unwrap(), pwd);
+}
+
+#[test]
+fn scripts_with_arguments_do_not_run_without_them() {
+	let h = Harness::new();
+	setup(&h);
+	let scripts = h.dotmgr().join("scripts");
+	h.write(
+		&scripts.join("10-install.sh"),
+		"# arguments: version [channel]\ntouch \"$HOME/install\"\n",
+	);
+	h.write(
+		&scripts.join("20-configure.sh"),
+		"# requires: 10-install\ntouch \"$HOME/configure\"\n",
+	);
+	h.write(&scripts.join("30-other.sh"), "touch \"$HOME/other\"\n");
+
+	let output = h.run(&["script", "run-all"]);
+	assert!(
+		output.contains("skipped (requires arguments: version [channel])"),
+		"{output}"
+	);
+	assert!(
+		output.contains("not run (requires 10-install.sh)"),
+		"{output}"
+	);
+	assert!(!h.home().join("install").exists());
+	assert!(!h.home().join("configure").exists());
+	assert!(h.home().join("other").exists());
+
+	let stderr = h.run_failing(&["script", "run", "20-configure"]);
+	assert!(
+		stderr.contains("10-install.sh (required by 20-configure.sh)"),
+		"{stderr}"
+	);
+	assert!(!h.home().join("install").exists());
+
+	h.run(&["script", "run", "10-install", "--", "1.0"]);
+	assert!(h.home().join("install").exists());
+}
+
+#[test]
+fn editor_command_line_has_arguments() {
+	let h = Harness::new();
+	setup(&h);
+	let script = h.dotmgr().join("scripts/10-tool.sh");
+	h.write(&script, "");
+	let editor = h.root.join("editor");
+	h.write_executable(&editor, "#!/bin/sh\necho \"$@\" > \"$HOME/edited\"\n");
+
+	let output = h
+		.command(&["script", "edit", "10-tool"])
+		.env("VISUAL", format!("{} --wait", editor.to_str().unwrap()))
+		.output()
+		.unwrap();
+	assert!(
+		output.status.success(),
+		"{}",
+		String::from_utf8_lossy(&output.stderr)
+	);
+	let edited = fs::read_to_string(h.home().join("edited")).unwrap();
+	assert_eq!(edited, format!("--wait {}\n", script.to_str().unwrap()));
+}
+
+#[test]
+fn scripts_require_scripts_of_other_categories() {
+	let h = Harness::new();
+	setup(&h);
+	h.write(
+		&h.dotmgr().join("scripts/10-base.sh"),
+		"touch \"$HOME/base\"\n",
+	);
+	h.write(
+		&h.dotmgr().join("scripts/20-other.sh"),
+		"touch \"$HOME/other\"\n",
+	);
+	h.write(
+		&h.dotmgr().join("scripts-work/10-vpn.sh"),
+		"# requires: default/10-base\ntouch \"$HOME/vpn\"\n",
+	);
+
+	let graph = h.run(&["script", "-c", "work", "graph"]);
+	assert_eq!(graph, "10-vpn.sh\n└── default/10-base.sh\n");
+
+	let output = h.run(&["script", "-c", "work", "run-all"]);
+	assert_eq!(output.matches(" ok ").count(), 2, "{output}");
+	assert!(h.home().join("base").exists());
+	assert!(h.home().join("vpn").exists());
+	assert!(!h.home().join("other").exists());
+}